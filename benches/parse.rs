@@ -0,0 +1,50 @@
+//! Throughput on large inputs, run with `cargo bench`, add
+//! `--features memchr` to compare the byte search.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use csi_parser::iter::CsiParser;
+use csi_parser::parser::parse;
+use csi_parser::stream::StreamParser;
+
+const SIZE: usize = 1 << 20;
+
+/// Log lines without any sequence.
+fn plain() -> String {
+    let line = "2024-05-01T12:00:00Z INFO  worker::pool: job 42 finished in 13.7ms, 3 retries\n";
+    line.repeat(SIZE / line.len())
+}
+
+/// Log lines with a colour change every few words.
+fn colored() -> String {
+    let line = "\x1b[2m2024-05-01T12:00:00Z\x1b[0m \x1b[32mINFO\x1b[0m \x1b[1;34mworker::pool\x1b[0m: job \x1b[38;5;208m42\x1b[0m finished\n";
+    line.repeat(SIZE / line.len())
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, text) in [("plain", plain()), ("colored", colored())] {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("parse", name), &text, |b, text| {
+            b.iter(|| parse(black_box(text)).len())
+        });
+        group.bench_with_input(BenchmarkId::new("csi_parser", name), &text, |b, text| {
+            b.iter(|| black_box(text.as_str()).csi_parser().count())
+        });
+        group.bench_with_input(BenchmarkId::new("stream", name), &text, |b, text| {
+            b.iter(|| {
+                let mut count = 0;
+                let mut parser = StreamParser::new();
+                for chunk in black_box(text.as_bytes()).chunks(4096) {
+                    parser.feed(chunk, |_| count += 1);
+                }
+                parser.flush(|_| count += 1);
+                count
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
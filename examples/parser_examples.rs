@@ -13,6 +13,25 @@ fn main() {
             Output::Escape(csi_seq) => {
                 println!("{}", csi_seq);
             }
+            Output::Esc(esc_seq) => {
+                println!("{}", esc_seq);
+            }
+            Output::Osc(osc) => {
+                println!("OSC {:?} {:?}", osc.command(), osc.payload);
+            }
+            Output::DcsHook(dcs) => {
+                println!("DCS {:?} {}", dcs.raw_params, dcs.dcs_type as char);
+            }
+            Output::Control(c0) => {
+                println!("{:?}", c0);
+            }
+            Output::Malformed(bytes) => {
+                println!("malformed {:?}", bytes);
+            }
+            Output::SingleShift(_)
+            | Output::SosPmApc(_)
+            | Output::DcsPut(_)
+            | Output::DcsUnhook => {}
         }
     }
 }
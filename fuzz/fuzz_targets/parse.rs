@@ -0,0 +1,56 @@
+//! Runs every parser over arbitrary input, run with `cargo fuzz run parse`.
+//!
+//! The first byte picks the config, whether small limits are set, and where
+//! the input is split for the stream and perform parsers.
+
+#![no_main]
+
+use csi_parser::config::ParserConfig;
+use csi_parser::iter::CsiParser;
+use csi_parser::parser::{parse_bytes_with, parse_bytes_with_diagnostics, parse_with_diagnostics};
+use csi_parser::perform::{Parser, Perform};
+use csi_parser::stream::StreamParser;
+use libfuzzer_sys::fuzz_target;
+
+struct Noop;
+
+impl Perform for Noop {}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&first, bytes)) = data.split_first() else {
+        return;
+    };
+    let limit = (first & 4 != 0).then_some(4);
+    let config = ParserConfig {
+        c1_controls: first & 1 != 0,
+        controls_in_text: first & 2 != 0,
+        max_params: limit,
+        max_param_value: limit.map(|limit| limit as u16),
+        max_sequence_len: limit,
+        max_string_len: limit,
+    };
+
+    let (matches, errors) = parse_bytes_with_diagnostics(bytes, config);
+    assert_eq!(matches, parse_bytes_with(bytes, config));
+    for error in &errors {
+        assert!(error.start < error.end && error.end <= bytes.len());
+    }
+    for m in &matches {
+        assert_eq!(m.csi_text.len(), m.end - m.start);
+    }
+    bytes.csi_parser_with(config).for_each(drop);
+
+    let text = String::from_utf8_lossy(bytes);
+    parse_with_diagnostics(&text, config);
+    text.csi_parser_with(config).for_each(drop);
+
+    let split = usize::from(first >> 3).min(bytes.len());
+    let mut stream = StreamParser::with_config(config);
+    stream.feed(&bytes[..split], |_| {});
+    stream.feed(&bytes[split..], |_| {});
+    stream.flush(|_| {});
+
+    let mut parser = Parser::with_config(config);
+    parser.advance(&mut Noop, &bytes[..split]);
+    parser.advance(&mut Noop, &bytes[split..]);
+});
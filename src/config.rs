@@ -0,0 +1,82 @@
+use crate::params::Params;
+
+/// Options shared by all the parsers.
+///
+/// ```
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::parser::matches;
+///
+/// let config = ParserConfig {
+///     c1_controls: true,
+///     ..ParserConfig::default()
+/// };
+/// let parsed: Vec<_> = matches("\u{9b}1m", config).collect();
+/// assert_eq!(parsed[0].csi_text, "\u{9b}1m");
+/// ```
+///
+/// Limits guard against untrusted input, a sequence going past one of them is
+/// skipped like other malformed sequences:
+///
+/// ```
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::parser::matches;
+///
+/// let config = ParserConfig {
+///     max_sequence_len: Some(16),
+///     ..ParserConfig::default()
+/// };
+/// let text = format!("\x1b[{}m\x1b[1m", "1".repeat(1000));
+/// let parsed: Vec<_> = matches(text.as_str(), config).collect();
+/// assert_eq!(parsed.len(), 1);
+/// assert_eq!(parsed[0].csi_text, "\x1b[1m");
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ParserConfig {
+    /// Recognise C1 controls (`0x80–0x9F`), such as the single-byte `CSI`
+    /// `0x9B`, `OSC` `0x9D`, `DCS` `0x90` and `ST` `0x9C`.
+    ///
+    /// Byte input uses the 8-bit form, `str` input uses the characters
+    /// `U+0080–U+009F`. Disabled by default, since in a UTF-8 byte stream
+    /// these bytes are continuation bytes.
+    pub c1_controls: bool,
+    /// Leave C0 controls (`CR`, `LF`, `BEL`, ...) inside
+    /// [`Output::Text`](crate::iter::Output::Text) instead of yielding them as
+    /// [`Output::Control`](crate::iter::Output::Control).
+    pub controls_in_text: bool,
+    /// Max number of parameters of a CSI or DCS sequence, not counting
+    /// sub-parameters. A sequence with more is ignored.
+    ///
+    /// Without a limit, parameters past
+    /// [`MAX_PARAMS`](crate::params::MAX_PARAMS) are dropped.
+    pub max_params: Option<usize>,
+    /// Max value of a parameter, a sequence with a larger one is ignored.
+    ///
+    /// Without a limit, values saturate at `u16::MAX`.
+    pub max_param_value: Option<u16>,
+    /// Max length in bytes of an escape or CSI sequence, or of the header of
+    /// a DCS sequence, from its introducer to its final byte. A longer
+    /// sequence is ignored up to its final byte.
+    pub max_sequence_len: Option<usize>,
+    /// Max length in bytes of the payload of an OSC, SOS, PM or APC string.
+    /// A longer string is ignored up to its terminator.
+    ///
+    /// The data string of a DCS sequence is not limited, since it is passed
+    /// on in parts as it comes.
+    pub max_string_len: Option<usize>,
+}
+
+impl ParserConfig {
+    /// Whether `params` go past [`ParserConfig::max_params`] or
+    /// [`ParserConfig::max_param_value`].
+    pub(crate) fn params_exceed(&self, params: &Params) -> bool {
+        let too_many = self.max_params.is_some_and(|max| params.len() > max);
+        let too_large = self.max_param_value.is_some_and(|max| {
+            params
+                .as_slice()
+                .iter()
+                .any(|value| value.is_some_and(|value| value > max))
+        });
+
+        too_many || too_large
+    }
+}
@@ -1,12 +1,16 @@
 use core::fmt::{Display, Formatter, Result as DisplayResult};
 
-use crate::parser::Match;
+use crate::intermediate_byte;
+use crate::list::List;
+use crate::params::{self, Params, MAX_PARAMS};
+use crate::parser::{EscMatch, Input, Match};
 
 /// A subset of CSI escape sequences. maybe add more.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum CSISequence {
     Escape,
-    Color(Option<usize>, Option<usize>, Option<usize>),
+    /// `CSI ... m` (SGR), the attributes in the order of the parameters.
+    Sgr(SgrAttrs),
     CursorPos(Option<usize>, Option<usize>),
     CursorUp(Option<usize>),
     CursorDown(Option<usize>),
@@ -16,121 +20,98 @@ pub enum CSISequence {
     CursorRestore,
     EraseDisplay(ClearMode),
     EraseLine(ClearMode),
-    EnableAttr(Attr),
-    ResetAttr(Attr),
+    /// `CSI Pm h` (SM)
+    SetMode(Modes<AnsiMode>),
+    /// `CSI Pm l` (RM)
+    ResetMode(Modes<AnsiMode>),
+    /// `CSI ? Pm h` (DECSET)
+    DecSet(Modes<DecMode>),
+    /// `CSI ? Pm l` (DECRST)
+    DecReset(Modes<DecMode>),
 }
 
-impl From<&Match<'_>> for CSISequence {
-    fn from(match_data: &Match<'_>) -> Self {
+impl<T: Input + ?Sized> From<&Match<'_, T>> for CSISequence {
+    fn from(match_data: &Match<'_, T>) -> Self {
         use CSISequence::*;
-        let params = match_data.parse_csi();
-        match match_data.csi_type {
+        let params = match_data.params();
+        let values = params.values();
+        let param = |index: usize| values.clone().nth(index).flatten().map(usize::from);
+        let first = param(0);
+        let last = params.len().checked_sub(1).and_then(param);
+
+        let intermediates = match_data.intermediates.as_bytes();
+
+        match (
+            match_data.private_marker,
+            intermediates,
+            match_data.csi_type,
+        ) {
             // 设置文本属性
-            b'm' => {
-                let foreground_color = str_to_usize(params.first());
-                // 兼容两个参数和一个参数的情况
-                let background_color = str_to_usize({
-                    // 参数数量大于2,才会有背景色参数
-                    if params.len() > 2 {
-                        params.get(1)
-                    } else {
-                        None
-                    }
-                });
-                let style = str_to_usize(if params.len() > 1 {
-                    // 参数数量大于1,必然有属性参数
-                    params.last()
-                } else {
-                    None
-                });
-                Color(foreground_color, background_color, style)
-            }
+            (None, [], b'm') => Sgr(SgrAttrs::from(params)),
 
             // 设置光标位置
-            b'H' => {
-                let row = str_to_usize(params.first());
-                let col = str_to_usize(params.last());
+            (None, [], b'H') => {
+                let row = first;
+                let col = last;
                 CursorPos(row, col)
             }
 
             // 光标上移
-            b'A' => {
-                let row = str_to_usize(params.first());
+            (None, [], b'A') => {
+                let row = first;
                 CursorUp(row)
             }
 
             // 光标下移
-            b'B' => {
-                let row = str_to_usize(params.first());
+            (None, [], b'B') => {
+                let row = first;
                 CursorDown(row)
             }
 
             // 光标右移
-            b'C' => {
-                let row = str_to_usize(params.first());
+            (None, [], b'C') => {
+                let row = first;
                 CursorForward(row)
             }
 
             // 光标左移
-            b'D' => {
-                let row = str_to_usize(params.first());
+            (None, [], b'D') => {
+                let row = first;
                 CursorBackward(row)
             }
 
             // 保存光标
-            b's' => CursorSave,
+            (None, [], b's') => CursorSave,
 
             // 恢复光标
-            b'u' => CursorRestore,
+            (None, [], b'u') => CursorRestore,
 
             // 清除屏幕
-            b'J' => {
-                let param = str_to_usize(params.first());
+            (None, [], b'J') => {
+                let param = first;
                 EraseDisplay(ClearMode::from(param))
             }
 
             // 清除行
-            b'K' => {
-                let param = str_to_usize(params.first());
+            (None, [], b'K') => {
+                let param = first;
                 EraseLine(ClearMode::from(param))
             }
 
-            // 启用属性
-            b'h' => {
-                let param = str_to_usize(params.first());
-                EnableAttr(Attr::from(param))
-            }
+            // 设置模式
+            (None, [], b'h') => SetMode(Modes::from(params)),
 
-            // 关闭属性
-            b'l' => {
-                let param = str_to_usize(params.first());
-                ResetAttr(Attr::from(param))
-            }
+            // 重置模式
+            (None, [], b'l') => ResetMode(Modes::from(params)),
 
-            // 未定义
-            _ => Escape,
-        }
-    }
-}
+            // 启用 DEC 私有模式
+            (Some(b'?'), [], b'h') => DecSet(Modes::from(params)),
 
-#[inline]
-/// CSI param to usize
-pub(crate) fn str_to_usize(num_str: Option<&&str>) -> Option<usize> {
-    match num_str {
-        None => None,
-        Some(str) => {
-            if str.is_empty() {
-                return None;
-            }
-
-            // 兼容`?`参数
-            if str.starts_with('?') {
-                return str_to_usize(Some(&&str[1..str.len()]));
-            }
+            // 关闭 DEC 私有模式
+            (Some(b'?'), [], b'l') => DecReset(Modes::from(params)),
 
-            // 其他情况暂不处理
-            // todo trace
-            str.parse::<usize>().ok()
+            // 未定义
+            _ => Escape,
         }
     }
 }
@@ -141,9 +122,7 @@ impl Display for CSISequence {
         use CSISequence::*;
         match self {
             Escape => write!(formatter, "\u{1b}"),
-            Color(strong, color, state) => {
-                write!(formatter, "[{:?};{:?};{:?}m", strong, color, state)
-            }
+            Sgr(attrs) => write!(formatter, "[{}m", attrs),
             CursorPos(row, col) => write!(formatter, "[{:?};{:?}H", row, col),
             CursorUp(amt) => write!(formatter, "[{:?}A", amt),
             CursorDown(amt) => write!(formatter, "[{:?}B", amt),
@@ -153,41 +132,880 @@ impl Display for CSISequence {
             CursorRestore => write!(formatter, "[u"),
             EraseDisplay(mode) => write!(formatter, "[{}J", mode),
             EraseLine(mode) => write!(formatter, "[{}K", mode),
-            EnableAttr(attr) => write!(formatter, "[?{}h", attr),
-            ResetAttr(attr) => write!(formatter, "[?{}l", attr),
+            SetMode(mode) => write!(formatter, "[{}h", mode),
+            ResetMode(mode) => write!(formatter, "[{}l", mode),
+            DecSet(mode) => write!(formatter, "[?{}h", mode),
+            DecReset(mode) => write!(formatter, "[?{}l", mode),
         }
     }
 }
 
-/// CSI `h` mode
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub enum Attr {
-    None = 0,
-    Cursor = 25,
-    AutoWrap = 7,
+/// Escape sequences without a CSI, ie `ESC 7` or `ESC ( 0`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EscSequence {
+    /// An escape sequence that is not listed here, ie `ESC $ ( C`.
+    Unknown {
+        /// The first two intermediate bytes, if any.
+        intermediates: [Option<u8>; 2],
+        final_byte: u8,
+    },
+    /// `ESC 7` (DECSC)
+    SaveCursor,
+    /// `ESC 8` (DECRC)
+    RestoreCursor,
+    /// `ESC c` (RIS)
+    Reset,
+    /// `ESC =` (DECKPAM)
+    KeypadApplicationMode,
+    /// `ESC >` (DECKPNM)
+    KeypadNumericMode,
+    /// `ESC D` (IND)
+    Index,
+    /// `ESC E` (NEL)
+    NextLine,
+    /// `ESC H` (HTS)
+    TabSet,
+    /// `ESC M` (RI)
+    ReverseIndex,
+    /// `ESC N` (SS2) that is not followed by a character, see
+    /// [`SingleShift`](crate::parser::SingleShift).
+    SingleShift2,
+    /// `ESC O` (SS3) that is not followed by a character.
+    SingleShift3,
+    /// `ESC Z` (DECID)
+    IdentifyTerminal,
+    /// `ESC SP F` (S7C1T)
+    SevenBitControls,
+    /// `ESC SP G` (S8C1T)
+    EightBitControls,
+    /// `ESC # 3` (DECDHL), top half
+    DoubleHeightTop,
+    /// `ESC # 4` (DECDHL), bottom half
+    DoubleHeightBottom,
+    /// `ESC # 5` (DECSWL)
+    SingleWidthLine,
+    /// `ESC # 6` (DECDWL)
+    DoubleWidthLine,
+    /// `ESC # 8` (DECALN)
+    ScreenAlignmentTest,
+    /// `ESC ( F`, `ESC ) F`, `ESC * F` or `ESC + F`, where `F` is the final
+    /// byte of the character set (ie `0` for DEC special graphics, `B` for
+    /// ASCII).
+    DesignateCharset(CharsetIndex, u8),
 }
 
-impl From<Option<usize>> for Attr {
-    fn from(value: Option<usize>) -> Self {
+/// The slot of [`EscSequence::DesignateCharset`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CharsetIndex {
+    G0,
+    G1,
+    G2,
+    G3,
+}
+
+impl<T: Input + ?Sized> From<&EscMatch<'_, T>> for EscSequence {
+    fn from(match_data: &EscMatch<'_, T>) -> Self {
+        use EscSequence::*;
+        // 跳过序列中的控制字符
+        let mut intermediates = match_data
+            .intermediates
+            .as_bytes()
+            .iter()
+            .copied()
+            .filter(|&byte| intermediate_byte(byte));
+        let intermediates = [intermediates.next(), intermediates.next()];
+        let unknown = Unknown {
+            intermediates,
+            final_byte: match_data.esc_type,
+        };
+        let intermediate = match intermediates {
+            [intermediate, None] => intermediate,
+            _ => return unknown,
+        };
+
+        match (intermediate, match_data.esc_type) {
+            (None, b'7') => SaveCursor,
+            (None, b'8') => RestoreCursor,
+            (None, b'c') => Reset,
+            (None, b'=') => KeypadApplicationMode,
+            (None, b'>') => KeypadNumericMode,
+            (None, b'D') => Index,
+            (None, b'E') => NextLine,
+            (None, b'H') => TabSet,
+            (None, b'M') => ReverseIndex,
+            (None, b'N') => SingleShift2,
+            (None, b'O') => SingleShift3,
+            (None, b'Z') => IdentifyTerminal,
+            (Some(b' '), b'F') => SevenBitControls,
+            (Some(b' '), b'G') => EightBitControls,
+            (Some(b'#'), b'3') => DoubleHeightTop,
+            (Some(b'#'), b'4') => DoubleHeightBottom,
+            (Some(b'#'), b'5') => SingleWidthLine,
+            (Some(b'#'), b'6') => DoubleWidthLine,
+            (Some(b'#'), b'8') => ScreenAlignmentTest,
+            // 选择字符集
+            (Some(b'('), charset) => DesignateCharset(CharsetIndex::G0, charset),
+            (Some(b')'), charset) => DesignateCharset(CharsetIndex::G1, charset),
+            (Some(b'*'), charset) => DesignateCharset(CharsetIndex::G2, charset),
+            (Some(b'+'), charset) => DesignateCharset(CharsetIndex::G3, charset),
+            // 未定义
+            _ => unknown,
+        }
+    }
+}
+
+impl Display for EscSequence {
+    /// 不打印0x1B,避免打印被转义
+    fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
+        use EscSequence::*;
+        match self {
+            Unknown {
+                intermediates,
+                final_byte,
+            } => {
+                for &intermediate in intermediates.iter().flatten() {
+                    write!(formatter, "{}", intermediate as char)?;
+                }
+                write!(formatter, "{}", *final_byte as char)
+            }
+            SaveCursor => write!(formatter, "7"),
+            RestoreCursor => write!(formatter, "8"),
+            Reset => write!(formatter, "c"),
+            KeypadApplicationMode => write!(formatter, "="),
+            KeypadNumericMode => write!(formatter, ">"),
+            Index => write!(formatter, "D"),
+            NextLine => write!(formatter, "E"),
+            TabSet => write!(formatter, "H"),
+            ReverseIndex => write!(formatter, "M"),
+            SingleShift2 => write!(formatter, "N"),
+            SingleShift3 => write!(formatter, "O"),
+            IdentifyTerminal => write!(formatter, "Z"),
+            SevenBitControls => write!(formatter, " F"),
+            EightBitControls => write!(formatter, " G"),
+            DoubleHeightTop => write!(formatter, "#3"),
+            DoubleHeightBottom => write!(formatter, "#4"),
+            SingleWidthLine => write!(formatter, "#5"),
+            DoubleWidthLine => write!(formatter, "#6"),
+            ScreenAlignmentTest => write!(formatter, "#8"),
+            DesignateCharset(index, charset) => {
+                let intermediate = match index {
+                    CharsetIndex::G0 => '(',
+                    CharsetIndex::G1 => ')',
+                    CharsetIndex::G2 => '*',
+                    CharsetIndex::G3 => '+',
+                };
+                write!(formatter, "{}{}", intermediate, *charset as char)
+            }
+        }
+    }
+}
+
+/// C0 control characters, except `ESC` which starts a sequence.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum C0 {
+    /// Null
+    Nul = 0x00,
+    /// Start of Heading
+    Soh = 0x01,
+    /// Start of Text
+    Stx = 0x02,
+    /// End of Text
+    Etx = 0x03,
+    /// End of Transmission
+    Eot = 0x04,
+    /// Enquiry
+    Enq = 0x05,
+    /// Acknowledge
+    Ack = 0x06,
+    /// Bell
+    Bel = 0x07,
+    /// Backspace
+    Bs = 0x08,
+    /// Horizontal Tab
+    Ht = 0x09,
+    /// Line Feed
+    Lf = 0x0a,
+    /// Vertical Tab
+    Vt = 0x0b,
+    /// Form Feed
+    Ff = 0x0c,
+    /// Carriage Return
+    Cr = 0x0d,
+    /// Shift Out
+    So = 0x0e,
+    /// Shift In
+    Si = 0x0f,
+    /// Data Link Escape
+    Dle = 0x10,
+    /// Device Control 1 (XON)
+    Dc1 = 0x11,
+    /// Device Control 2
+    Dc2 = 0x12,
+    /// Device Control 3 (XOFF)
+    Dc3 = 0x13,
+    /// Device Control 4
+    Dc4 = 0x14,
+    /// Negative Acknowledge
+    Nak = 0x15,
+    /// Synchronous Idle
+    Syn = 0x16,
+    /// End of Transmission Block
+    Etb = 0x17,
+    /// Cancel, also aborts a sequence
+    Can = 0x18,
+    /// End of Medium
+    Em = 0x19,
+    /// Substitute, also aborts a sequence
+    Sub = 0x1a,
+    /// File Separator
+    Fs = 0x1c,
+    /// Group Separator
+    Gs = 0x1d,
+    /// Record Separator
+    Rs = 0x1e,
+    /// Unit Separator
+    Us = 0x1f,
+}
+
+impl TryFrom<u8> for C0 {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        use C0::*;
+        Ok(match byte {
+            0x00 => Nul,
+            0x01 => Soh,
+            0x02 => Stx,
+            0x03 => Etx,
+            0x04 => Eot,
+            0x05 => Enq,
+            0x06 => Ack,
+            0x07 => Bel,
+            0x08 => Bs,
+            0x09 => Ht,
+            0x0a => Lf,
+            0x0b => Vt,
+            0x0c => Ff,
+            0x0d => Cr,
+            0x0e => So,
+            0x0f => Si,
+            0x10 => Dle,
+            0x11 => Dc1,
+            0x12 => Dc2,
+            0x13 => Dc3,
+            0x14 => Dc4,
+            0x15 => Nak,
+            0x16 => Syn,
+            0x17 => Etb,
+            0x18 => Can,
+            0x19 => Em,
+            0x1a => Sub,
+            0x1c => Fs,
+            0x1d => Gs,
+            0x1e => Rs,
+            0x1f => Us,
+            _ => return Err(byte),
+        })
+    }
+}
+
+/// ANSI modes of [`CSISequence::SetMode`] and [`CSISequence::ResetMode`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AnsiMode {
+    /// `2` (KAM), keyboard locked.
+    KeyboardAction,
+    /// `4` (IRM), characters are inserted instead of replacing the ones
+    /// under the cursor.
+    Insert,
+    /// `12` (SRM), local echo off.
+    SendReceive,
+    /// `20` (LNM), line feed also moves to the first column.
+    LineFeedNewLine,
+    /// A mode that is not listed here.
+    Unknown(u16),
+}
+
+impl From<u16> for AnsiMode {
+    fn from(mode: u16) -> Self {
+        use AnsiMode::*;
+        match mode {
+            2 => KeyboardAction,
+            4 => Insert,
+            12 => SendReceive,
+            20 => LineFeedNewLine,
+            _ => Unknown(mode),
+        }
+    }
+}
+
+impl From<AnsiMode> for u16 {
+    fn from(mode: AnsiMode) -> Self {
+        use AnsiMode::*;
+        match mode {
+            KeyboardAction => 2,
+            Insert => 4,
+            SendReceive => 12,
+            LineFeedNewLine => 20,
+            Unknown(mode) => mode,
+        }
+    }
+}
+
+impl Display for AnsiMode {
+    fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
+        write!(formatter, "{}", u16::from(*self))
+    }
+}
+
+/// The modes of a set or reset sequence, in the order of the parameters, at
+/// most [`MAX_PARAMS`] are kept.
+///
+/// ```
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::enums::{CSISequence, DecMode};
+/// use csi_parser::parser::matches;
+///
+/// let parsed: Vec<_> = matches("\x1b[?1049;1006;2004h", ParserConfig::default()).collect();
+/// let CSISequence::DecSet(modes) = CSISequence::from(&parsed[0]) else {
+///     unreachable!()
+/// };
+/// assert_eq!(
+///     modes.as_slice(),
+///     &[DecMode::AltScreenSaveCursor, DecMode::SgrMouse, DecMode::BracketedPaste]
+/// );
+/// ```
+pub type Modes<M> = List<M, MAX_PARAMS>;
+
+impl<M: Copy + From<u16>> From<&Params> for Modes<M> {
+    fn from(params: &Params) -> Self {
+        let mut modes = Modes::default();
+        // 没有参数等同于 0
+        if params.is_empty() {
+            modes.push(M::from(0));
+        }
+        for mode in params.values() {
+            modes.push(M::from(mode.unwrap_or(0)));
+        }
+        modes
+    }
+}
+
+/// DEC private modes of [`CSISequence::DecSet`] and
+/// [`CSISequence::DecReset`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DecMode {
+    /// `1` (DECCKM), cursor keys send application sequences.
+    CursorKeys,
+    /// `2` (DECANM), reset to enter VT52 mode.
+    Ansi,
+    /// `3` (DECCOLM), 132 columns.
+    Column,
+    /// `4` (DECSCLM), smooth scrolling.
+    SmoothScroll,
+    /// `5` (DECSCNM), reverse video of the whole screen.
+    ReverseScreen,
+    /// `6` (DECOM), cursor positions are relative to the scrolling margins.
+    Origin,
+    /// `7` (DECAWM)
+    AutoWrap,
+    /// `8` (DECARM)
+    AutoRepeat,
+    /// `9`, X10 mouse reporting.
+    X10Mouse,
+    /// `12`, blinking cursor.
+    CursorBlink,
+    /// `25` (DECTCEM), visible cursor.
+    ShowCursor,
+    /// `47`, alternate screen buffer.
+    AltScreen,
+    /// `66` (DECNKM), keypad sends application sequences.
+    ApplicationKeypad,
+    /// `69` (DECLRMM), left and right margins can be set.
+    LeftRightMargin,
+    /// `1000`, mouse button press and release reporting.
+    MouseClick,
+    /// `1001`, mouse highlight tracking.
+    MouseHighlight,
+    /// `1002`, mouse motion reporting while a button is pressed.
+    MouseDrag,
+    /// `1003`, all mouse motion reporting.
+    MouseMotion,
+    /// `1004`, focus in and out reporting.
+    FocusEvents,
+    /// `1005`, UTF-8 mouse coordinates.
+    Utf8Mouse,
+    /// `1006`, SGR mouse coordinates.
+    SgrMouse,
+    /// `1007`, wheel scrolls the alternate screen with cursor keys.
+    AlternateScroll,
+    /// `1015`, urxvt mouse coordinates.
+    UrxvtMouse,
+    /// `1016`, SGR mouse coordinates in pixels.
+    SgrPixelMouse,
+    /// `1047`, alternate screen buffer, cleared when leaving it.
+    AltScreenClear,
+    /// `1048`, saves the cursor as with DECSC.
+    SaveCursor,
+    /// `1049`, saves the cursor and switches to a cleared alternate screen
+    /// buffer.
+    AltScreenSaveCursor,
+    /// `2004`, bracketed paste.
+    BracketedPaste,
+    /// `2026`, synchronized output.
+    SynchronizedOutput,
+    /// A mode that is not listed here.
+    Unknown(u16),
+}
+
+impl From<u16> for DecMode {
+    fn from(mode: u16) -> Self {
+        use DecMode::*;
+        match mode {
+            1 => CursorKeys,
+            2 => Ansi,
+            3 => Column,
+            4 => SmoothScroll,
+            5 => ReverseScreen,
+            6 => Origin,
+            7 => AutoWrap,
+            8 => AutoRepeat,
+            9 => X10Mouse,
+            12 => CursorBlink,
+            25 => ShowCursor,
+            47 => AltScreen,
+            66 => ApplicationKeypad,
+            69 => LeftRightMargin,
+            1000 => MouseClick,
+            1001 => MouseHighlight,
+            1002 => MouseDrag,
+            1003 => MouseMotion,
+            1004 => FocusEvents,
+            1005 => Utf8Mouse,
+            1006 => SgrMouse,
+            1007 => AlternateScroll,
+            1015 => UrxvtMouse,
+            1016 => SgrPixelMouse,
+            1047 => AltScreenClear,
+            1048 => SaveCursor,
+            1049 => AltScreenSaveCursor,
+            2004 => BracketedPaste,
+            2026 => SynchronizedOutput,
+            _ => Unknown(mode),
+        }
+    }
+}
+
+impl From<DecMode> for u16 {
+    fn from(mode: DecMode) -> Self {
+        use DecMode::*;
+        match mode {
+            CursorKeys => 1,
+            Ansi => 2,
+            Column => 3,
+            SmoothScroll => 4,
+            ReverseScreen => 5,
+            Origin => 6,
+            AutoWrap => 7,
+            AutoRepeat => 8,
+            X10Mouse => 9,
+            CursorBlink => 12,
+            ShowCursor => 25,
+            AltScreen => 47,
+            ApplicationKeypad => 66,
+            LeftRightMargin => 69,
+            MouseClick => 1000,
+            MouseHighlight => 1001,
+            MouseDrag => 1002,
+            MouseMotion => 1003,
+            FocusEvents => 1004,
+            Utf8Mouse => 1005,
+            SgrMouse => 1006,
+            AlternateScroll => 1007,
+            UrxvtMouse => 1015,
+            SgrPixelMouse => 1016,
+            AltScreenClear => 1047,
+            SaveCursor => 1048,
+            AltScreenSaveCursor => 1049,
+            BracketedPaste => 2004,
+            SynchronizedOutput => 2026,
+            Unknown(mode) => mode,
+        }
+    }
+}
+
+impl Display for DecMode {
+    fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
+        write!(formatter, "{}", u16::from(*self))
+    }
+}
+
+/// A graphic rendition attribute of a SGR sequence, see
+/// [`CSISequence::Sgr`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SgrAttr {
+    /// `0` or an empty parameter, resets all attributes.
+    Reset,
+    /// `1`
+    Bold,
+    /// `2`
+    Faint,
+    /// `3`
+    Italic,
+    /// `4`, or `4:0` to `4:5` with a style.
+    Underline(UnderlineStyle),
+    /// `5`
+    SlowBlink,
+    /// `6`
+    RapidBlink,
+    /// `7`
+    Inverse,
+    /// `8`
+    Conceal,
+    /// `9`
+    CrossedOut,
+    /// `10–19`, `0` is the primary font and `1–9` the alternative fonts.
+    Font(u8),
+    /// `20`
+    Fraktur,
+    /// `21`
+    DoubleUnderline,
+    /// `22`, neither bold nor faint.
+    NormalIntensity,
+    /// `23`, neither italic nor fraktur.
+    NotItalic,
+    /// `24`
+    NotUnderlined,
+    /// `25`
+    NotBlinking,
+    /// `27`
+    NotInverse,
+    /// `28`
+    NotConcealed,
+    /// `29`
+    NotCrossedOut,
+    /// `30–39` and `90–97`.
+    Foreground(Color),
+    /// `40–49` and `100–107`.
+    Background(Color),
+    /// `58` and `59`, the named colours are only set with the `5` form.
+    UnderlineColor(Color),
+    /// `53`
+    Overlined,
+    /// `55`
+    NotOverlined,
+    /// A parameter that is not listed here, or a `38`, `48` or `58` colour
+    /// that can't be decoded. The extra parameters of such a colour are
+    /// skipped.
+    Unknown(u16),
+}
+
+impl SgrAttr {
+    /// Decodes the parameter `group`, taking the following parameters it
+    /// needs from `rest`.
+    fn decode(group: &[Option<u16>], rest: &mut params::Iter<'_>) -> Self {
+        use SgrAttr::*;
+        // 空参数等同于 0
+        let value = group[0].unwrap_or(0);
         match value {
-            None => Attr::None,
-            Some(mode) => match mode {
-                0 => Attr::None,
-                25 => Attr::Cursor,
-                7 => Attr::AutoWrap,
-                _ => Attr::None,
+            0 => Reset,
+            1 => Bold,
+            2 => Faint,
+            3 => Italic,
+            4 => match group.get(1) {
+                None => Underline(UnderlineStyle::Single),
+                Some(style) => Underline(UnderlineStyle::from(style.unwrap_or(0))),
             },
+            5 => SlowBlink,
+            6 => RapidBlink,
+            7 => Inverse,
+            8 => Conceal,
+            9 => CrossedOut,
+            10..=19 => Font((value - 10) as u8),
+            20 => Fraktur,
+            21 => DoubleUnderline,
+            22 => NormalIntensity,
+            23 => NotItalic,
+            24 => NotUnderlined,
+            25 => NotBlinking,
+            27 => NotInverse,
+            28 => NotConcealed,
+            29 => NotCrossedOut,
+            30..=37 => Foreground(Color::Named(NAMED_COLORS[usize::from(value - 30)])),
+            39 => Foreground(Color::Default),
+            40..=47 => Background(Color::Named(NAMED_COLORS[usize::from(value - 40)])),
+            49 => Background(Color::Default),
+            53 => Overlined,
+            55 => NotOverlined,
+            90..=97 => Foreground(Color::Named(NAMED_COLORS[usize::from(value - 90 + 8)])),
+            100..=107 => Background(Color::Named(NAMED_COLORS[usize::from(value - 100 + 8)])),
+            38 | 48 | 58 => match (value, Color::decode(group, rest)) {
+                (38, Some(color)) => Foreground(color),
+                (48, Some(color)) => Background(color),
+                (58, Some(color)) => UnderlineColor(color),
+                _ => Unknown(value),
+            },
+            59 => UnderlineColor(Color::Default),
+            _ => Unknown(value),
         }
     }
 }
 
-impl Display for Attr {
+impl Display for SgrAttr {
     fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
+        use SgrAttr::*;
+        let value: u16 = match *self {
+            Reset => 0,
+            Bold => 1,
+            Faint => 2,
+            Italic => 3,
+            Underline(UnderlineStyle::Single) => 4,
+            Underline(style) => return write!(formatter, "4:{}", u16::from(style)),
+            SlowBlink => 5,
+            RapidBlink => 6,
+            Inverse => 7,
+            Conceal => 8,
+            CrossedOut => 9,
+            Font(font) => 10 + u16::from(font),
+            Fraktur => 20,
+            DoubleUnderline => 21,
+            NormalIntensity => 22,
+            NotItalic => 23,
+            NotUnderlined => 24,
+            NotBlinking => 25,
+            NotInverse => 27,
+            NotConcealed => 28,
+            NotCrossedOut => 29,
+            Foreground(color) => return color.write(formatter, 30),
+            Background(color) => return color.write(formatter, 40),
+            UnderlineColor(color) => return color.write(formatter, 50),
+            Overlined => 53,
+            NotOverlined => 55,
+            Unknown(value) => value,
+        };
+        write!(formatter, "{}", value)
+    }
+}
+
+/// The style of [`SgrAttr::Underline`], set with `4:0` to `4:5` by Kitty,
+/// WezTerm, VTE and iTerm.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnderlineStyle {
+    /// `4:0`, same as `24`.
+    None,
+    /// `4` or `4:1`
+    Single,
+    /// `4:2`
+    Double,
+    /// `4:3`
+    Curly,
+    /// `4:4`
+    Dotted,
+    /// `4:5`
+    Dashed,
+    /// A style that is not listed here, written back as it is.
+    Unknown(u16),
+}
+
+impl From<u16> for UnderlineStyle {
+    fn from(style: u16) -> Self {
+        use UnderlineStyle::*;
+        match style {
+            0 => None,
+            1 => Single,
+            2 => Double,
+            3 => Curly,
+            4 => Dotted,
+            5 => Dashed,
+            _ => Unknown(style),
+        }
+    }
+}
+
+impl From<UnderlineStyle> for u16 {
+    fn from(style: UnderlineStyle) -> Self {
+        use UnderlineStyle::*;
+        match style {
+            None => 0,
+            Single => 1,
+            Double => 2,
+            Curly => 3,
+            Dotted => 4,
+            Dashed => 5,
+            Unknown(style) => style,
+        }
+    }
+}
+
+/// A colour of [`SgrAttr::Foreground`], [`SgrAttr::Background`] or
+/// [`SgrAttr::UnderlineColor`].
+///
+/// The `38`, `48` and `58` colours are decoded from both the `;` form used
+/// by most programs, ie `38;5;208` and `38;2;r;g;b`, and the ITU T.416 `:`
+/// form, ie `38:5:208` and `38:2:id:r:g:b` where the colour space `id` is
+/// ignored and may be left out.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Color {
+    /// The default colour of the terminal, ie `39`.
+    Default,
+    /// One of the 16 colours with their own parameter, ie `31` or `91`.
+    Named(NamedColor),
+    /// `38;5;n`, an entry of the 256 colour palette.
+    Indexed(u8),
+    /// `38;2;r;g;b`
+    Rgb(u8, u8, u8),
+}
+
+/// The colours of `30–37` and their bright variants of `90–97`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum NamedColor {
+    Black = 0,
+    Red = 1,
+    Green = 2,
+    Yellow = 3,
+    Blue = 4,
+    Magenta = 5,
+    Cyan = 6,
+    White = 7,
+    BrightBlack = 8,
+    BrightRed = 9,
+    BrightGreen = 10,
+    BrightYellow = 11,
+    BrightBlue = 12,
+    BrightMagenta = 13,
+    BrightCyan = 14,
+    BrightWhite = 15,
+}
+
+// 按颜色序号排列
+const NAMED_COLORS: [NamedColor; 16] = {
+    use NamedColor::*;
+    [
+        Black,
+        Red,
+        Green,
+        Yellow,
+        Blue,
+        Magenta,
+        Cyan,
+        White,
+        BrightBlack,
+        BrightRed,
+        BrightGreen,
+        BrightYellow,
+        BrightBlue,
+        BrightMagenta,
+        BrightCyan,
+        BrightWhite,
+    ]
+};
+
+impl TryFrom<u8> for NamedColor {
+    type Error = u8;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        NAMED_COLORS.get(usize::from(index)).copied().ok_or(index)
+    }
+}
+
+impl Color {
+    /// Decodes the colour after a `38`, `48` or `58` parameter, from the
+    /// sub-parameters of `group` or from the parameters taken from `rest`.
+    fn decode(group: &[Option<u16>], rest: &mut params::Iter<'_>) -> Option<Self> {
+        // 空参数等同于 0
+        let byte = |value: Option<u16>| u8::try_from(value.unwrap_or(0)).ok();
+
+        if group.len() > 1 {
+            return match group[1..] {
+                [Some(5), index] => Some(Color::Indexed(byte(index)?)),
+                // 颜色空间可以省略
+                [Some(2), r, g, b] | [Some(2), _, r, g, b, ..] => {
+                    Some(Color::Rgb(byte(r)?, byte(g)?, byte(b)?))
+                }
+                _ => None,
+            };
+        }
+
+        let kind = match rest.clone().next() {
+            Some(&[Some(kind @ (2 | 5))]) => kind,
+            _ => return None,
+        };
+        rest.next();
+        // 颜色参数不能带子参数
+        let mut next = || match rest.next() {
+            Some(&[value]) => Some(value),
+            _ => None,
+        };
+        if kind == 5 {
+            Some(Color::Indexed(byte(next()?)?))
+        } else {
+            let (r, g, b) = (next(), next(), next());
+            Some(Color::Rgb(byte(r?)?, byte(g?)?, byte(b?)?))
+        }
+    }
+
+    /// Writes the parameters of the colour, `base` is `30`, `40` or `50`.
+    fn write(self, formatter: &mut Formatter, base: u16) -> DisplayResult {
         match self {
-            Attr::None => write!(formatter, "{}", 0),
-            Attr::Cursor => write!(formatter, "{}", 25),
-            Attr::AutoWrap => write!(formatter, "{}", 7),
+            Color::Default => write!(formatter, "{}", base + 9),
+            // 下划线颜色没有单独的参数
+            Color::Named(color) if base == 50 => write!(formatter, "58;5;{}", color as u8),
+            Color::Named(color) if (color as u8) < 8 => {
+                write!(formatter, "{}", base + u16::from(color as u8))
+            }
+            Color::Named(color) => write!(formatter, "{}", base + 60 + u16::from(color as u8) - 8),
+            Color::Indexed(index) => write!(formatter, "{};5;{}", base + 8, index),
+            Color::Rgb(r, g, b) => write!(formatter, "{};2;{};{};{}", base + 8, r, g, b),
+        }
+    }
+}
+
+/// Max number of attributes kept for a single SGR sequence.
+///
+/// Real SGR sequences rarely set more than a handful of attributes, a small
+/// inline list keeps [`CSISequence`] and every [`Output`](crate::iter::Output)
+/// cheap to copy.
+pub const MAX_SGR_ATTRS: usize = 16;
+
+/// The attributes of a SGR sequence, decoded from left to right, at most
+/// [`MAX_SGR_ATTRS`] are kept.
+///
+/// ```
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::enums::{CSISequence, Color, NamedColor, SgrAttr, UnderlineStyle};
+/// use csi_parser::parser::matches;
+///
+/// let parsed: Vec<_> = matches("\x1b[1;4;31;42m", ParserConfig::default()).collect();
+/// let CSISequence::Sgr(attrs) = CSISequence::from(&parsed[0]) else {
+///     unreachable!()
+/// };
+/// assert_eq!(
+///     attrs.as_slice(),
+///     &[
+///         SgrAttr::Bold,
+///         SgrAttr::Underline(UnderlineStyle::Single),
+///         SgrAttr::Foreground(Color::Named(NamedColor::Red)),
+///         SgrAttr::Background(Color::Named(NamedColor::Green)),
+///     ]
+/// );
+/// ```
+pub type SgrAttrs = List<SgrAttr, MAX_SGR_ATTRS>;
+
+impl From<&Params> for SgrAttrs {
+    fn from(params: &Params) -> Self {
+        let mut attrs = SgrAttrs::default();
+        // 没有参数等同于 0
+        if params.is_empty() {
+            attrs.push(SgrAttr::Reset);
+            return attrs;
         }
+
+        let mut groups = params.iter();
+        while let Some(group) = groups.next() {
+            attrs.push(SgrAttr::decode(group, &mut groups));
+        }
+        attrs
     }
 }
 
@@ -225,13 +1043,18 @@ impl Display for ClearMode {
 
 #[cfg(test)]
 mod tests {
-    #[cfg(all(not(feature = "std"), feature = "no_std"))]
-    use alloc::vec;
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
+    use alloc::{format, string::ToString, vec, vec::Vec};
 
     use super::*;
+    #[cfg(feature = "alloc")]
     use crate::enums::CSISequence::*;
+    #[cfg(feature = "alloc")]
+    use crate::iter::{CsiParser, Output};
+    #[cfg(feature = "alloc")]
     use crate::parser::parse;
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn parse_test() {
         let ansi_text = "Hello, \x1b[;;4mworld\x1b[0K!\x1b[?7h";
@@ -245,13 +1068,18 @@ mod tests {
         assert_eq!(
             csi_seqs,
             vec![
-                Color(None, None, Some(4)),
+                Sgr(SgrAttrs::from_slice(&[
+                    SgrAttr::Reset,
+                    SgrAttr::Reset,
+                    SgrAttr::Underline(UnderlineStyle::Single)
+                ])),
                 EraseLine(ClearMode::After),
-                EnableAttr(Attr::AutoWrap),
+                DecSet(Modes::from_slice(&[DecMode::AutoWrap])),
             ]
         );
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn parse_string_with_different_chars() {
         let t = "👋, \x1b[31;4m🌍\x1b[0m!";
@@ -264,10 +1092,17 @@ mod tests {
 
         assert_eq!(
             csi_seqs,
-            vec![Color(Some(31), None, Some(4)), Color(Some(0), None, None),]
+            vec![
+                Sgr(SgrAttrs::from_slice(&[
+                    SgrAttr::Foreground(Color::Named(NamedColor::Red)),
+                    SgrAttr::Underline(UnderlineStyle::Single)
+                ])),
+                Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset])),
+            ]
         );
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn parse_string_with_set_cursor_ansi() {
         let t = "\x1b[31Ahello!";
@@ -280,6 +1115,7 @@ mod tests {
         assert_eq!(csi_seqs, vec![CursorUp(Some(31)),]);
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn malformed_escape() {
         let mut csi_seqs = vec![];
@@ -291,6 +1127,87 @@ mod tests {
         assert_eq!(csi_seqs, vec![]);
     }
 
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn subparams() {
+        let x = parse("\x1b[4:3A\x1b[38:2::255:0:0m");
+
+        let csi_seqs: Vec<CSISequence> = x.iter().map(|m| m.into()).collect();
+        assert_eq!(
+            csi_seqs,
+            vec![
+                CursorUp(Some(4)),
+                Sgr(SgrAttrs::from_slice(&[SgrAttr::Foreground(Color::Rgb(
+                    255, 0, 0
+                ))]))
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn private_marker() {
+        let x = parse("\x1b[?25h\x1b[25h\x1b[>c\x1b[c\x1b[2 q\x1b[?2J");
+
+        let csi_seqs: Vec<CSISequence> = x.iter().map(|m| m.into()).collect();
+        assert_eq!(
+            csi_seqs,
+            vec![
+                DecSet(Modes::from_slice(&[DecMode::ShowCursor])),
+                SetMode(Modes::from_slice(&[AnsiMode::Unknown(25)])),
+                Escape,
+                Escape,
+                Escape,
+                Escape
+            ]
+        );
+        assert_eq!(x[2].private_marker, Some(b'>'));
+        assert_eq!(x[4].intermediates, " ");
+        assert_eq!(x[4].params().as_slice(), &[Some(2)]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn modes() {
+        let x = parse("\x1b[4h\x1b[?4h\x1b[20l\x1b[?1049l\x1b[?2026h\x1b[?9999h\x1b[?1049;1006:1;;2004h\x1b[4;20l\x1b[h");
+
+        let csi_seqs: Vec<CSISequence> = x.iter().map(|m| m.into()).collect();
+        assert_eq!(
+            csi_seqs,
+            vec![
+                SetMode(Modes::from_slice(&[AnsiMode::Insert])),
+                DecSet(Modes::from_slice(&[DecMode::SmoothScroll])),
+                ResetMode(Modes::from_slice(&[AnsiMode::LineFeedNewLine])),
+                DecReset(Modes::from_slice(&[DecMode::AltScreenSaveCursor])),
+                DecSet(Modes::from_slice(&[DecMode::SynchronizedOutput])),
+                DecSet(Modes::from_slice(&[DecMode::Unknown(9999)])),
+                DecSet(Modes::from_slice(&[
+                    DecMode::AltScreenSaveCursor,
+                    DecMode::SgrMouse,
+                    DecMode::Unknown(0),
+                    DecMode::BracketedPaste,
+                ])),
+                ResetMode(Modes::from_slice(&[
+                    AnsiMode::Insert,
+                    AnsiMode::LineFeedNewLine
+                ])),
+                SetMode(Modes::from_slice(&[AnsiMode::Unknown(0)])),
+            ]
+        );
+        for (m, seq) in x.iter().zip(&csi_seqs).take(6) {
+            assert_eq!(format!("\x1b{}", seq), m.csi_text);
+        }
+        assert_eq!(csi_seqs[6].to_string(), "[?1049;1006;0;2004h");
+        assert_eq!(csi_seqs[7].to_string(), "[4;20l");
+
+        // 编号和模式一一对应
+        for mode in 0..=u16::MAX {
+            assert_eq!(u16::from(DecMode::from(mode)), mode);
+            assert_eq!(u16::from(AnsiMode::from(mode)), mode);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
     #[test]
     fn reset_color() {
         let x = parse("oops\x1b[0m");
@@ -301,6 +1218,241 @@ mod tests {
             csi_seqs.push(csi_seq);
         }
 
-        assert_eq!(csi_seqs, vec![Color(Some(0), None, None),]);
+        assert_eq!(
+            csi_seqs,
+            vec![Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset])),]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn sgr() {
+        use SgrAttr::*;
+
+        let x = parse("\x1b[1;4;31;42m\x1b[m\x1b[2;3;5;6;7;8;9;11;20;21;53m\x1b[22;23;24;25;27;28;29;39;49;55;10m\x1b[91;107;38;5;208;1;48;2;1;2;3;26m");
+        let attrs: Vec<SgrAttrs> = x
+            .iter()
+            .map(|m| match m.into() {
+                Sgr(attrs) => attrs,
+                seq => panic!("{:?}", seq),
+            })
+            .collect();
+        let expected: [&[SgrAttr]; 5] = [
+            &[
+                Bold,
+                Underline(UnderlineStyle::Single),
+                Foreground(Color::Named(NamedColor::Red)),
+                Background(Color::Named(NamedColor::Green)),
+            ],
+            &[Reset],
+            &[
+                Faint,
+                Italic,
+                SlowBlink,
+                RapidBlink,
+                Inverse,
+                Conceal,
+                CrossedOut,
+                Font(1),
+                Fraktur,
+                DoubleUnderline,
+                Overlined,
+            ],
+            &[
+                NormalIntensity,
+                NotItalic,
+                NotUnderlined,
+                NotBlinking,
+                NotInverse,
+                NotConcealed,
+                NotCrossedOut,
+                Foreground(Color::Default),
+                Background(Color::Default),
+                NotOverlined,
+                Font(0),
+            ],
+            &[
+                Foreground(Color::Named(NamedColor::BrightRed)),
+                Background(Color::Named(NamedColor::BrightWhite)),
+                Foreground(Color::Indexed(208)),
+                Bold,
+                Background(Color::Rgb(1, 2, 3)),
+                Unknown(26),
+            ],
+        ];
+        let attrs: Vec<&[SgrAttr]> = attrs.iter().map(SgrAttrs::as_slice).collect();
+        assert_eq!(attrs, expected);
+
+        // 写回原来的参数
+        for m in &x[..4] {
+            assert_eq!(
+                format!("\x1b{}", CSISequence::from(m)),
+                m.csi_text.replace("[m", "[0m")
+            );
+        }
+    }
+
+    #[test]
+    fn sgr_capacity() {
+        // 超过容量的属性被丢弃
+        let attrs = SgrAttrs::from_slice(&[SgrAttr::Bold; MAX_SGR_ATTRS + 1]);
+        assert_eq!(attrs.as_slice(), &[SgrAttr::Bold; MAX_SGR_ATTRS]);
+
+        let params = Params::parse(b"1;2;3;4;5;6;7;8;9;1;2;3;4;5;6;7;8;9");
+        let attrs = SgrAttrs::from(&params);
+        assert_eq!(attrs.len(), MAX_SGR_ATTRS);
+        assert_eq!(attrs.as_slice()[MAX_SGR_ATTRS - 1], SgrAttr::Inverse);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn colors() {
+        use SgrAttr::*;
+
+        let attrs = |text: &str| match CSISequence::from(&parse(text)[0]) {
+            Sgr(attrs) => attrs,
+            seq => panic!("{:?}", seq),
+        };
+
+        // 两种编码
+        for text in [
+            "\x1b[38;5;208;48;2;10;20;30;58;2;1;2;3m",
+            "\x1b[38:5:208;48:2::10:20:30;58:2:0:1:2:3m",
+            "\x1b[38:5:208;48:2:10:20:30;58:2:1:1:2:3m",
+        ] {
+            assert_eq!(
+                attrs(text).as_slice(),
+                &[
+                    Foreground(Color::Indexed(208)),
+                    Background(Color::Rgb(10, 20, 30)),
+                    UnderlineColor(Color::Rgb(1, 2, 3)),
+                ],
+                "{:?}",
+                text
+            );
+        }
+
+        // 无法解析的颜色
+        assert_eq!(
+            attrs("\x1b[38;5;256;1;48;2;1;2;3:4;1;38;3;1m").as_slice(),
+            &[
+                Unknown(38),
+                Bold,
+                Unknown(48),
+                Bold,
+                Unknown(38),
+                Italic,
+                Bold
+            ]
+        );
+        assert_eq!(attrs("\x1b[38:5m\x1b[48;2;1m").as_slice(), &[Unknown(38)]);
+        assert_eq!(attrs("\x1b[48;2;1m").as_slice(), &[Unknown(48)]);
+        assert_eq!(
+            attrs("\x1b[38;5;m").as_slice(),
+            &[Foreground(Color::Indexed(0))]
+        );
+
+        let text = "\x1b[31;91;40;107;38;5;1;48;2;1;2;3;58;5;9;39;49m";
+        assert_eq!(format!("\x1b{}", CSISequence::from(&parse(text)[0])), text);
+        assert_eq!(
+            Sgr(SgrAttrs::from_slice(&[UnderlineColor(Color::Named(
+                NamedColor::BrightRed
+            ))]))
+            .to_string(),
+            "[58;5;9m"
+        );
+        assert_eq!(NamedColor::try_from(9), Ok(NamedColor::BrightRed));
+        assert_eq!(NamedColor::try_from(16), Err(16));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn underline() {
+        use SgrAttr::*;
+
+        let x = parse("\x1b[4:0;4:1;4:2;4:3;4:4;4:5;4:6;4;4:;21;24m\x1b[58:5:1;59m");
+        let attrs: Vec<SgrAttrs> = x
+            .iter()
+            .map(|m| match m.into() {
+                Sgr(attrs) => attrs,
+                seq => panic!("{:?}", seq),
+            })
+            .collect();
+
+        assert_eq!(
+            attrs[0].as_slice(),
+            &[
+                Underline(UnderlineStyle::None),
+                Underline(UnderlineStyle::Single),
+                Underline(UnderlineStyle::Double),
+                Underline(UnderlineStyle::Curly),
+                Underline(UnderlineStyle::Dotted),
+                Underline(UnderlineStyle::Dashed),
+                Underline(UnderlineStyle::Unknown(6)),
+                Underline(UnderlineStyle::Single),
+                Underline(UnderlineStyle::None),
+                DoubleUnderline,
+                NotUnderlined,
+            ]
+        );
+        assert_eq!(
+            attrs[1].as_slice(),
+            &[
+                UnderlineColor(Color::Indexed(1)),
+                UnderlineColor(Color::Default)
+            ]
+        );
+        assert_eq!(
+            attrs[0].to_string(),
+            "4:0;4;4:2;4:3;4:4;4:5;4:6;4;4:0;21;24"
+        );
+        assert_eq!(attrs[1].to_string(), "58;5;1;59");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn esc_sequences() {
+        let seqs: Vec<EscSequence> = "\x1b7\x1b(0\x1b)B\x1b#8\x1b c\x1bM\x1b(\n0\x1b$(C"
+            .csi_parser()
+            .filter_map(|o| match o {
+                Output::Esc(seq) => Some(seq),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            seqs,
+            vec![
+                EscSequence::SaveCursor,
+                EscSequence::DesignateCharset(CharsetIndex::G0, b'0'),
+                EscSequence::DesignateCharset(CharsetIndex::G1, b'B'),
+                EscSequence::ScreenAlignmentTest,
+                EscSequence::Unknown {
+                    intermediates: [Some(b' '), None],
+                    final_byte: b'c',
+                },
+                EscSequence::ReverseIndex,
+                // 序列中的控制字符不影响中间字节
+                EscSequence::DesignateCharset(CharsetIndex::G0, b'0'),
+                EscSequence::Unknown {
+                    intermediates: [Some(b'$'), Some(b'(')],
+                    final_byte: b'C',
+                },
+            ]
+        );
+        assert_eq!(seqs[4].to_string(), " c");
+        assert_eq!(seqs[7].to_string(), "$(C");
+        assert_eq!(
+            EscSequence::DesignateCharset(CharsetIndex::G0, b'0').to_string(),
+            "(0"
+        );
+    }
+
+    #[test]
+    fn c0() {
+        assert_eq!(C0::try_from(b'\n'), Ok(C0::Lf));
+        assert_eq!(C0::Bel as u8, 0x07);
+        assert_eq!(C0::try_from(0x1b), Err(0x1b));
+        assert_eq!(C0::try_from(b'a'), Err(b'a'));
     }
 }
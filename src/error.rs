@@ -0,0 +1,57 @@
+//! Problems found in the input, see `parser::parse_with_diagnostics` with
+//! the `alloc` feature.
+//!
+//! The parsers never fail: a malformed sequence is skipped and parsing goes
+//! on after it. Diagnostics only tell where the input went wrong.
+
+use core::fmt;
+
+/// What is wrong with a sequence.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ParseErrorKind {
+    /// The sequence ended before its final byte or terminator, at the end of
+    /// the input or because `CAN`, `SUB`, `ESC` or a C1 control aborted it.
+    TruncatedSequence,
+    /// The sequence contains a byte that is not allowed before its final
+    /// byte, ie `ESC [ 1 < 2 m`, so it is ignored up to its final byte or
+    /// terminator.
+    InvalidFinalByte,
+    /// The sequence has more parameters than
+    /// [`MAX_PARAMS`](crate::params::MAX_PARAMS), or a parameter larger than
+    /// `u16::MAX`. The extra parameters are dropped and large values are
+    /// saturated.
+    OversizeParams,
+    /// The sequence goes past one of the limits of
+    /// [`ParserConfig`](crate::config::ParserConfig), so it is ignored up to
+    /// its final byte or terminator.
+    LimitExceeded,
+}
+
+/// A problem in the input and its byte span.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ParseErrorKind::TruncatedSequence => "truncated sequence",
+            ParseErrorKind::InvalidFinalByte => "invalid byte before the final byte",
+            ParseErrorKind::OversizeParams => "too many or too large parameters",
+            ParseErrorKind::LimitExceeded => "sequence exceeds the configured limits",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.kind, self.start, self.end)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
@@ -0,0 +1,175 @@
+//! Finds the next byte that needs parsing in the ground state, the text
+//! before it can be skipped as a whole.
+//!
+//! With the `memchr` feature a single `ESC` (and the UTF-8 lead byte of C1
+//! controls) is searched with `memchr`, otherwise the input is checked one
+//! machine word at a time.
+
+use crate::scanner::C1;
+
+const WORD: usize = core::mem::size_of::<usize>();
+// 每个字节都是 0x01
+const LO: usize = usize::MAX / 0xff;
+// 每个字节都是 0x80
+const HI: usize = LO * 0x80;
+
+/// Index of the first byte of `bytes` that may start a sequence, or a C0
+/// control if `controls` is set, `bytes.len()` if there is none.
+#[inline]
+pub(crate) fn find_special(bytes: &[u8], controls: bool, c1: C1) -> usize {
+    match (controls, c1) {
+        (false, C1::Disabled) => find_byte(bytes, 0x1b),
+        (false, C1::Utf8) => find_byte2(bytes, 0x1b, 0xc2),
+        _ => find_by_word(
+            bytes,
+            |word| {
+                let c1_word = match c1 {
+                    C1::Disabled => false,
+                    // 0x80–0x9F 的高 3 位为 100
+                    C1::Byte => has_zero((word & (LO * 0xe0)) ^ (LO * 0x80)),
+                    C1::Utf8 => has_zero(word ^ (LO * 0xc2)),
+                };
+                has_less(word, 0x20) || c1_word
+            },
+            |byte| is_special(byte, controls, c1),
+        ),
+    }
+}
+
+#[inline]
+fn is_special(byte: u8, controls: bool, c1: C1) -> bool {
+    match byte {
+        0x1b => true,
+        0x00..=0x1f => controls,
+        0x80..=0x9f => c1 == C1::Byte,
+        0xc2 => c1 == C1::Utf8,
+        _ => false,
+    }
+}
+
+#[cfg(feature = "memchr")]
+#[inline]
+fn find_byte(bytes: &[u8], needle: u8) -> usize {
+    memchr::memchr(needle, bytes).unwrap_or(bytes.len())
+}
+
+#[cfg(not(feature = "memchr"))]
+#[inline]
+fn find_byte(bytes: &[u8], needle: u8) -> usize {
+    find_by_word(
+        bytes,
+        |word| has_zero(word ^ (LO * usize::from(needle))),
+        |byte| byte == needle,
+    )
+}
+
+#[cfg(feature = "memchr")]
+#[inline]
+fn find_byte2(bytes: &[u8], first: u8, second: u8) -> usize {
+    memchr::memchr2(first, second, bytes).unwrap_or(bytes.len())
+}
+
+#[cfg(not(feature = "memchr"))]
+#[inline]
+fn find_byte2(bytes: &[u8], first: u8, second: u8) -> usize {
+    find_by_word(
+        bytes,
+        |word| {
+            has_zero(word ^ (LO * usize::from(first)))
+                || has_zero(word ^ (LO * usize::from(second)))
+        },
+        |byte| byte == first || byte == second,
+    )
+}
+
+/// Skips whole words for which `maybe` is false, then finds the byte with
+/// `matches`. `maybe` may have false positives but no false negatives.
+#[inline]
+fn find_by_word(
+    bytes: &[u8],
+    maybe: impl Fn(usize) -> bool,
+    matches: impl Fn(u8) -> bool,
+) -> usize {
+    let mut pos = 0;
+    for chunk in bytes.chunks_exact(WORD) {
+        // 字节顺序不影响判断
+        let word = chunk
+            .iter()
+            .fold(0, |word, &byte| word << 8 | usize::from(byte));
+        if maybe(word) {
+            if let Some(index) = chunk.iter().position(|&byte| matches(byte)) {
+                return pos + index;
+            }
+        }
+        pos += WORD;
+    }
+
+    bytes[pos..]
+        .iter()
+        .position(|&byte| matches(byte))
+        .map_or(bytes.len(), |index| pos + index)
+}
+
+/// Whether a byte of `word` is zero.
+#[inline]
+fn has_zero(word: usize) -> bool {
+    word.wrapping_sub(LO) & !word & HI != 0
+}
+
+/// Whether a byte of `word` is less than `n`, which is at most `0x80`.
+#[inline]
+fn has_less(word: usize, n: u8) -> bool {
+    word.wrapping_sub(LO * usize::from(n)) & !word & HI != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The first special byte, one byte at a time.
+    fn naive(bytes: &[u8], controls: bool, c1: C1) -> usize {
+        bytes
+            .iter()
+            .position(|&byte| is_special(byte, controls, c1))
+            .unwrap_or(bytes.len())
+    }
+
+    #[test]
+    fn find_special_bytes() {
+        let text = "plain text, long enough for a few words, é ü".as_bytes();
+        let mut bytes = text.to_vec();
+        for c1 in [C1::Disabled, C1::Byte, C1::Utf8] {
+            for controls in [false, true] {
+                assert_eq!(find_special(text, controls, c1), text.len());
+            }
+        }
+
+        // 在每个位置放入每个字节
+        for pos in 0..text.len() {
+            for byte in 0..=255 {
+                bytes[pos] = byte;
+                for c1 in [C1::Disabled, C1::Byte, C1::Utf8] {
+                    for controls in [false, true] {
+                        assert_eq!(
+                            find_special(&bytes, controls, c1),
+                            naive(&bytes, controls, c1),
+                            "byte {:#x} at {}",
+                            byte,
+                            pos
+                        );
+                    }
+                }
+            }
+            bytes[pos] = text[pos];
+        }
+    }
+
+    #[test]
+    fn word_tricks() {
+        assert!(has_zero((LO * 0x41) & !0xff));
+        assert!(!has_zero(LO * 0x41));
+        assert!(has_less((LO * 0x41) ^ 0x41, 0x20));
+        assert!(!has_less(LO * 0x20, 0x20));
+        assert!(!has_less(HI, 0x20));
+    }
+}
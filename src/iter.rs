@@ -1,148 +1,202 @@
-use crate::enums::CSISequence;
-use crate::parser::{parse, Match};
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Output<'a> {
-    Text(&'a str),
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{borrow::Cow, string::String};
+
+#[cfg(feature = "std")]
+use std::{borrow::Cow, string::String};
+
+use crate::config::ParserConfig;
+use crate::enums::{CSISequence, EscSequence, C0};
+use crate::params::Params;
+use crate::parser::{DcsMatch, EscMatch, Input, Match, OscMatch, SingleShift, StringMatch};
+use crate::scanner::{Scanner, Token, TokenKind, C1};
+
+/// An item of [`CsiIterator`], the text is either `&str` or `&[u8]`.
+#[derive(Debug, PartialEq)]
+pub enum Output<'a, T: ?Sized = str> {
+    Text(&'a T),
     Escape(CSISequence),
+    Esc(EscSequence),
+    SingleShift(SingleShift),
+    /// A C0 control, see [`ParserConfig::controls_in_text`]. A control
+    /// executed inside a CSI or escape sequence, ie `ESC [ 1 LF m`, comes
+    /// right before the sequence.
+    Control(C0),
+    Osc(OscMatch<'a, T>),
+    /// A SOS, PM or APC string, which can be skipped with
+    /// [`Output::is_sos_pm_apc`].
+    SosPmApc(StringMatch<'a, T>),
+    /// The header of a DCS sequence, followed by its data string in one or
+    /// more [`Output::DcsPut`] and then [`Output::DcsUnhook`].
+    DcsHook(DcsMatch<'a, T>),
+    DcsPut(&'a T),
+    /// The DCS sequence is terminated by `ST`, aborted, or cut off at the end
+    /// of the input.
+    DcsUnhook,
+    /// The bytes of a sequence that a terminal ignores: aborted by `CAN`,
+    /// `SUB` or another sequence, unterminated at the end of the input, or
+    /// containing an invalid byte such as `ESC [ 1 < 2 m`. Parsing goes on
+    /// right after it.
+    Malformed(&'a T),
+}
+
+impl<T: ?Sized> Clone for Output<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-impl Output<'_> {
+impl<T: ?Sized> Copy for Output<'_, T> {}
+
+impl<T: ?Sized> Output<'_, T> {
     #[inline]
     pub fn is_text(&self) -> bool {
-        match self {
-            Output::Text(_) => true,
-            Output::Escape(_) => false,
-        }
+        matches!(self, Output::Text(_))
     }
 
+    /// Whether this is a complete escape sequence, the header of a DCS
+    /// sequence included but not its data string or its end.
     #[inline]
     pub fn is_esc(&self) -> bool {
-        !self.is_text()
+        matches!(
+            self,
+            Output::Escape(_)
+                | Output::Esc(_)
+                | Output::SingleShift(_)
+                | Output::Osc(_)
+                | Output::SosPmApc(_)
+                | Output::DcsHook(_)
+        )
+    }
+
+    /// ```
+    /// use csi_parser::iter::{CsiParser, Output};
+    ///
+    /// let text = "a\x1b_Gf=24;AAAA\x1b\\b";
+    /// let out: Vec<_> = text.csi_parser().filter(|o| !o.is_sos_pm_apc()).collect();
+    /// assert_eq!(out, vec![Output::Text("a"), Output::Text("b")]);
+    /// ```
+    #[inline]
+    pub fn is_sos_pm_apc(&self) -> bool {
+        matches!(self, Output::SosPmApc(_))
+    }
+}
+
+impl<'a, T: Input + ?Sized> Output<'a, T> {
+    /// `params` are the parameters of a CSI token.
+    pub(crate) fn new(text: &'a T, token: Token, params: &Params) -> Self {
+        match token.kind {
+            TokenKind::Text => Output::Text(text.slice(token.start..token.end)),
+            TokenKind::Csi => Output::Escape((&Match::new(text, token, params)).into()),
+            TokenKind::Esc => Output::Esc((&EscMatch::new(text, token)).into()),
+            TokenKind::SingleShift => Output::SingleShift(SingleShift::new(text.as_bytes(), token)),
+            TokenKind::Osc(terminator) => Output::Osc(OscMatch::new(text, token, terminator)),
+            TokenKind::SosPmApc => Output::SosPmApc(StringMatch::new(text, token)),
+            TokenKind::Control => match C0::try_from(text.as_bytes()[token.start]) {
+                Ok(c0) => Output::Control(c0),
+                Err(_) => Output::Text(text.slice(token.start..token.end)),
+            },
+            TokenKind::DcsHook => Output::DcsHook(DcsMatch::new(text, token)),
+            TokenKind::DcsPut => Output::DcsPut(text.slice(token.start..token.end)),
+            TokenKind::DcsUnhook { .. } => Output::DcsUnhook,
+            TokenKind::Malformed(_) => Output::Malformed(text.slice(token.start..token.end)),
+        }
     }
 }
 
-/// Once this trait is implemented, we can parse CSI and implement an iterator.
+#[cfg(feature = "alloc")]
+impl<'a> Output<'a, [u8]> {
+    /// The text as `str`, invalid UTF-8 is replaced by `U+FFFD`.
+    ///
+    /// ```
+    /// use csi_parser::iter::{CsiParser, Output};
+    ///
+    /// let out: Vec<_> = b"\x1b[1mcaf\xe9".csi_parser().collect();
+    /// assert_eq!(out[1], Output::Text(&b"caf\xe9"[..]));
+    /// assert_eq!(out[1].text_lossy().unwrap(), "caf\u{FFFD}");
+    /// ```
+    pub fn text_lossy(&self) -> Option<Cow<'a, str>> {
+        match self {
+            Output::Text(text) => Some(String::from_utf8_lossy(text)),
+            _ => None,
+        }
+    }
+}
+
+/// Splits the input into [`Output`] items, parsing it as the items are
+/// requested.
+///
 /// ```
-/// #[cfg(all(not(feature = "std"), feature = "alloc"))]
-/// impl CsiParser for String {
-///     fn csi_parser(&self) -> CsiIterator {
-///         CsiIterator {
-///            data: self,
-///            matches: Some(parse(self)),
-///            index: 0,
-///            index_of_data: 0,
-///         }
-///     }
-/// }
+/// use csi_parser::iter::{CsiParser, Output};
+///
+/// let text = "\x1b[1mbold\x1b[0m".repeat(1000);
+/// let first: Vec<_> = text.csi_parser().take(2).collect();
+/// assert_eq!(first[1], Output::Text("bold"));
 /// ```
 pub trait CsiParser {
-    fn csi_parser(&self) -> CsiIterator;
+    fn csi_parser(&self) -> CsiIterator<'_, Self> {
+        self.csi_parser_with(ParserConfig::default())
+    }
+
+    fn csi_parser_with(&self, config: ParserConfig) -> CsiIterator<'_, Self>;
 }
 
 impl CsiParser for str {
-    fn csi_parser(&self) -> CsiIterator {
-        CsiIterator {
-            data: self,
-            matches: Some(parse(self)),
-            index: 0,
-            index_of_data: 0,
-        }
+    fn csi_parser_with(&self, config: ParserConfig) -> CsiIterator<'_> {
+        CsiIterator::new(self, config)
+    }
+}
+
+/// Text items are byte slices, which don't have to be valid UTF-8.
+impl CsiParser for [u8] {
+    fn csi_parser_with(&self, config: ParserConfig) -> CsiIterator<'_, [u8]> {
+        CsiIterator::new(self, config)
     }
 }
 
 /// the csi iterator
-pub struct CsiIterator<'a> {
+pub struct CsiIterator<'a, T: ?Sized = str> {
     // original str
-    data: &'a str,
-    // the matches of the data
-    #[cfg(all(not(feature = "std"), feature = "no_std"))]
-    matches: Option<alloc::vec::Vec<Match<'a>>>,
-    #[cfg(feature = "std")]
-    matches: Option<std::vec::Vec<Match<'a>>>,
-    // csi seq index
-    index: usize,
-    // the index of the data
-    index_of_data: usize,
+    data: &'a T,
+    // 按需扫描
+    scanner: Scanner<'a>,
 }
 
-impl<'a> Iterator for CsiIterator<'a> {
-    type Item = Output<'a>;
+impl<'a, T: Input + ?Sized> CsiIterator<'a, T> {
+    fn new(data: &'a T, config: ParserConfig) -> Self {
+        let scanner = Scanner::new(data.as_bytes(), C1::new::<T>(config))
+            .limits(config)
+            .split_controls(!config.controls_in_text)
+            .report_errors();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.data.is_empty() {
-            return None;
-        }
-
-        // 获取所有的CSI,初始化数据
-        if self.matches.is_none() {
-            self.matches = Some(parse(self.data));
-            self.index = 0;
-            self.index_of_data = 0;
-        }
+        CsiIterator { data, scanner }
+    }
+}
 
-        if let Some(csi_matches) = &self.matches {
-            // 解析出来的csi序列不存在,则全部是文本
-            if csi_matches.is_empty() {
-                // 用index标识是否遍历完成
-                return if self.index == 0 {
-                    self.index += 1;
-                    Some(Output::Text(self.data))
-                } else {
-                    None
-                };
-            }
-
-            return if self.index < csi_matches.len() {
-                let csi_item = &csi_matches[self.index];
-
-                // csi seq
-                let index_of_data = self.index_of_data;
-                #[allow(clippy::comparison_chain)]
-                if self.index_of_data < csi_item.start {
-                    self.index_of_data = csi_item.start;
-                    Some(Output::Text(&self.data[index_of_data..csi_item.start]))
-                } else if self.index_of_data == csi_item.start {
-                    self.index_of_data = csi_item.end;
-                    Some(Output::Escape(csi_item.into()))
-                } else {
-                    // data已经遍历完成
-                    if index_of_data >= self.data.len() {
-                        return None;
-                    }
-
-                    self.index += 1;
-                    // 已经是最后一个csi
-                    if self.index >= csi_matches.len() {
-                        return Some(Output::Text(&self.data[index_of_data..]));
-                    }
-
-                    let next = &csi_matches[self.index];
-                    self.index_of_data = next.start;
-
-                    Some(Output::Text(&self.data[index_of_data..next.start]))
-                }
-            } else {
-                None
-            };
-        }
+impl<'a, T: Input + ?Sized> Iterator for CsiIterator<'a, T> {
+    type Item = Output<'a, T>;
 
-        None
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.scanner.next()?;
+        Some(Output::new(self.data, token, self.scanner.params()))
     }
 }
 
-#[cfg(test)]
+// 测试用例需要分配内存
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
-    #[cfg(all(not(feature = "std"), feature = "no_std"))]
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
     use alloc::vec;
-    #[cfg(all(not(feature = "std"), feature = "no_std"))]
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
     use alloc::vec::Vec;
 
     #[cfg(feature = "std")]
     use std::vec::Vec;
 
     use super::*;
-    use crate::enums::CSISequence::Color;
+    use crate::enums::CSISequence::Sgr;
+    use crate::enums::{Color, NamedColor, SgrAttr, SgrAttrs};
+    use crate::parser::{StringKind, StringTerminator};
 
     #[test]
     fn test_iter() {
@@ -153,7 +207,7 @@ mod tests {
             out,
             vec![
                 Output::Text("hello,world"),
-                Output::Escape(Color(None, None, None))
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset])))
             ]
         );
     }
@@ -164,6 +218,15 @@ mod tests {
         let out: Vec<Output> = text.csi_parser().filter(Output::is_text).collect();
 
         assert_eq!(out, vec![Output::Text("hello,world")]);
+
+        let out: Vec<Output> = "\x1b7a\n\x1b[1<2m\x1bPqx\x1b\\"
+            .csi_parser()
+            .filter(Output::is_esc)
+            .collect();
+        assert_eq!(out.len(), 2);
+        assert!(matches!(out[..], [Output::Esc(_), Output::DcsHook(_)]));
+        assert!(!Output::<str>::Control(C0::Lf).is_esc());
+        assert!(!Output::Malformed("\x1b[").is_esc());
     }
 
     #[test]
@@ -171,7 +234,12 @@ mod tests {
         let text = "\x1b[31m";
         let out: Vec<Output> = text.csi_parser().collect();
 
-        assert_eq!(out, vec![Output::Escape(Color(Some(31), None, None))]);
+        assert_eq!(
+            out,
+            vec![Output::Escape(Sgr(SgrAttrs::from_slice(&[
+                SgrAttr::Foreground(Color::Named(NamedColor::Red))
+            ])))]
+        );
     }
 
     #[test]
@@ -207,7 +275,9 @@ mod tests {
             out,
             vec![
                 Output::Text("hello world"),
-                Output::Escape(Color(Some(31), None, None))
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Foreground(
+                    Color::Named(NamedColor::Red)
+                )])))
             ]
         );
     }
@@ -220,10 +290,248 @@ mod tests {
         assert_eq!(
             out,
             vec![
-                Output::Escape(Color(None, None, None)),
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset]))),
                 Output::Text("hello world"),
-                Output::Escape(Color(Some(31), None, None))
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Foreground(
+                    Color::Named(NamedColor::Red)
+                )])))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_bytes() {
+        let text = b"\xff\x1b[31mhello\xe4\x1b[m";
+        let out: Vec<Output<[u8]>> = text.csi_parser().collect();
+
+        assert_eq!(
+            out,
+            vec![
+                Output::Text(&b"\xff"[..]),
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Foreground(
+                    Color::Named(NamedColor::Red)
+                )]))),
+                Output::Text(&b"hello\xe4"[..]),
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset])))
             ]
         );
+        assert_eq!(out[0].text_lossy().unwrap(), "\u{FFFD}");
+        assert_eq!(out[1].text_lossy(), None);
+    }
+
+    #[test]
+    fn test_iter_osc() {
+        let text = "\x1b]0;title\x07hello\x1b]8;;http://a\x1b\\world\x1b]8;;\x1b\\";
+        let out: Vec<Output> = text.csi_parser().collect();
+
+        assert_eq!(
+            out,
+            vec![
+                Output::Osc(OscMatch {
+                    start: 0,
+                    end: 10,
+                    payload: "0;title",
+                    terminator: StringTerminator::Bel,
+                }),
+                Output::Text("hello"),
+                Output::Osc(OscMatch {
+                    start: 15,
+                    end: 30,
+                    payload: "8;;http://a",
+                    terminator: StringTerminator::St,
+                }),
+                Output::Text("world"),
+                Output::Osc(OscMatch {
+                    start: 35,
+                    end: 42,
+                    payload: "8;;",
+                    terminator: StringTerminator::St,
+                }),
+            ]
+        );
+
+        let Output::Osc(link) = out[2] else {
+            unreachable!()
+        };
+        assert_eq!(link.command(), Some(8));
+        assert_eq!(link.params().collect::<Vec<_>>(), vec!["", "http://a"]);
+
+        let Output::Osc(title) = out[0] else {
+            unreachable!()
+        };
+        assert_eq!(title.params().collect::<Vec<_>>(), vec!["title"]);
+    }
+
+    #[test]
+    fn test_iter_sos_pm_apc() {
+        let text = "\x1bXsos\x1b\\\x1b^pm\x1b\\\x1b_Ga=q\x1b\\";
+        let kinds: Vec<_> = text
+            .csi_parser()
+            .map(|o| match o {
+                Output::SosPmApc(string) => (string.kind, string.payload),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (StringKind::Sos, "sos"),
+                (StringKind::Pm, "pm"),
+                (StringKind::Apc, "Ga=q"),
+            ]
+        );
+
+        let bytes = b"\x9fGa=q\x9c";
+        let config = ParserConfig {
+            c1_controls: true,
+            ..ParserConfig::default()
+        };
+        let out: Vec<_> = bytes.csi_parser_with(config).collect();
+        assert_eq!(
+            out,
+            vec![Output::SosPmApc(StringMatch {
+                start: 0,
+                end: 6,
+                kind: StringKind::Apc,
+                payload: &b"Ga=q"[..],
+            })]
+        );
+    }
+
+    #[test]
+    fn test_iter_controls() {
+        let text = "a\r\n\x07\x1b[mb";
+        let out: Vec<Output> = text.csi_parser().collect();
+
+        assert_eq!(
+            out,
+            vec![
+                Output::Text("a"),
+                Output::Control(C0::Cr),
+                Output::Control(C0::Lf),
+                Output::Control(C0::Bel),
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset]))),
+                Output::Text("b"),
+            ]
+        );
+
+        let config = ParserConfig {
+            controls_in_text: true,
+            ..ParserConfig::default()
+        };
+        let out: Vec<Output> = text.csi_parser_with(config).collect();
+        assert_eq!(out[0], Output::Text("a\r\n\x07"));
+
+        let out: Vec<Output> = "a\x1b[1\n2mb".csi_parser().collect();
+        assert_eq!(
+            out,
+            vec![
+                Output::Text("a"),
+                Output::Control(C0::Lf),
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Font(2)]))),
+                Output::Text("b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_lazy() {
+        let text = "\x1b[31mhello\x1b[m world".repeat(1000);
+        let mut iter = text.csi_parser();
+
+        assert_eq!(
+            iter.next(),
+            Some(Output::Escape(Sgr(SgrAttrs::from_slice(&[
+                SgrAttr::Foreground(Color::Named(NamedColor::Red))
+            ]))))
+        );
+        assert_eq!(iter.next(), Some(Output::Text("hello")));
+        // 只扫描到了第二个序列
+        assert_eq!(iter.scanner.consumed(), 13);
+        assert_eq!(iter.count(), 4 * 1000 - 2);
+    }
+
+    #[test]
+    fn test_iter_malformed() {
+        let text = "a\x1b[1<2mb\x1b[3\x18\x1b[mc\x1b]0;t";
+        let out: Vec<Output> = text.csi_parser().collect();
+
+        assert_eq!(
+            out,
+            vec![
+                Output::Text("a"),
+                Output::Malformed("\x1b[1<2m"),
+                Output::Text("b"),
+                Output::Malformed("\x1b[3"),
+                Output::Control(C0::Can),
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset]))),
+                Output::Text("c"),
+                Output::Malformed("\x1b]0;t"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_dcs() {
+        let text = "a\x1bPq#0;2\x1b\\b\x1bP1$r0m\x18c\x1bPqx\x1b[m";
+        let out: Vec<Output> = text.csi_parser().collect();
+
+        assert_eq!(
+            out,
+            vec![
+                Output::Text("a"),
+                Output::DcsHook(DcsMatch {
+                    start: 1,
+                    end: 4,
+                    raw_params: "",
+                    intermediates: "",
+                    dcs_type: b'q',
+                }),
+                Output::DcsPut("#0;2"),
+                Output::DcsUnhook,
+                Output::Text("b"),
+                Output::DcsHook(DcsMatch {
+                    start: 11,
+                    end: 16,
+                    raw_params: "1",
+                    intermediates: "$",
+                    dcs_type: b'r',
+                }),
+                Output::DcsPut("0m"),
+                // CAN 中止序列
+                Output::DcsUnhook,
+                Output::Control(C0::Can),
+                Output::Text("c"),
+                Output::DcsHook(DcsMatch {
+                    start: 20,
+                    end: 23,
+                    raw_params: "",
+                    intermediates: "",
+                    dcs_type: b'q',
+                }),
+                Output::DcsPut("x"),
+                Output::DcsUnhook,
+                Output::Escape(Sgr(SgrAttrs::from_slice(&[SgrAttr::Reset]))),
+            ]
+        );
+    }
+    #[test]
+    fn test_iter_dcs_end() {
+        let out: Vec<Output> = "\x1bP1$r0m\x1b".csi_parser().collect();
+        assert_eq!(
+            out[1..],
+            [
+                Output::DcsPut("0m"),
+                Output::DcsUnhook,
+                // 与文本末尾的 ESC 相同
+                Output::Malformed("\x1b"),
+            ]
+        );
+        let out: Vec<Output> = "abc\x1b".csi_parser().collect();
+        assert_eq!(out[1], Output::Malformed("\x1b"));
+
+        let out: Vec<Output> = "\x1bPq".csi_parser().collect();
+        assert_eq!(out[1..], [Output::DcsUnhook]);
     }
 }
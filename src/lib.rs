@@ -6,19 +6,35 @@
 //!
 //! See the [rs docs.](https://docs.rs/csi_parser/)
 //! Look at progress and contribute on [github.](https://github.com/YageGeng/csi_parser)
+//!
+//! Without the default `std` feature the crate is `no_std`. The parsers,
+//! [`iter::CsiIterator`], [`parser::matches`] and [`perform::Parser`], then
+//! only need `core`, while the `alloc` feature adds the functions returning a
+//! `Vec` and `stream::StreamParser`.
 
-#![cfg_attr(feature = "no_std", no_std)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-#[cfg(all(not(feature = "std"), feature = "no_std"))]
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
 extern crate alloc;
 
+pub mod config;
 pub mod enums;
+pub mod error;
+mod find;
 pub mod iter;
+pub mod list;
+pub mod params;
 pub mod parser;
+pub mod perform;
+mod scanner;
+mod state;
+#[cfg(feature = "alloc")]
+pub mod stream;
 
 // ESC is 0x1B
+#[cfg(feature = "alloc")]
 pub(crate) const CSI: &str = "\u{1B}[";
-pub(crate) const SEPARATOR: &str = ";";
+pub(crate) const SEPARATOR: u8 = b';';
 
 /// CSI final byte `0x40–0x7E`
 /// [CSI](https://en.wikipedia.org/wiki/ANSI_escape_code#CSI_(Control_Sequence_Introducer)_sequences)
@@ -0,0 +1,127 @@
+use core::fmt::{self, Display, Formatter, Result as DisplayResult};
+
+/// A list of at most `N` items stored inline, so it stays `Copy` and needs no
+/// allocation. The items past `N` are dropped.
+///
+/// Used by [`Modes`](crate::enums::Modes) and
+/// [`SgrAttrs`](crate::enums::SgrAttrs).
+#[derive(Clone, Copy)]
+pub struct List<T, const N: usize> {
+    // 第一次添加时用该元素填满,空列表不需要占位的值
+    items: Option<[T; N]>,
+    // N 不超过 u8::MAX
+    len: u8,
+}
+
+impl<T, const N: usize> Default for List<T, N> {
+    fn default() -> Self {
+        List {
+            items: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for List<T, N> {
+    fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
+        // 只打印有效部分
+        formatter.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for List<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        // 只比较有效部分
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for List<T, N> {}
+
+impl<T: Copy, const N: usize> List<T, N> {
+    /// Copies `items`, the items past `N` are dropped.
+    pub fn from_slice(items: &[T]) -> Self {
+        let mut list = List::default();
+        for &item in items {
+            list.push(item);
+        }
+        list
+    }
+
+    #[inline]
+    pub(crate) fn push(&mut self, item: T) {
+        let len = self.len();
+        if len < N.min(usize::from(u8::MAX)) {
+            self.items.get_or_insert([item; N])[len] = item;
+            self.len += 1;
+        }
+    }
+}
+
+impl<T, const N: usize> List<T, N> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        usize::from(self.len)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        match &self.items {
+            Some(items) => &items[..self.len()],
+            None => &[],
+        }
+    }
+
+    #[inline]
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a List<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Display, const N: usize> Display for List<T, N> {
+    fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
+        for (index, item) in self.iter().enumerate() {
+            if index > 0 {
+                write!(formatter, ";")?;
+            }
+            write!(formatter, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::List;
+
+    #[test]
+    fn capacity() {
+        let list = List::<u8, 3>::default();
+        assert!(list.is_empty());
+        assert_eq!(list.as_slice(), &[]);
+
+        let list = List::<u8, 3>::from_slice(&[1, 2, 3, 4]);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.as_slice(), &[1, 2, 3]);
+
+        // 只比较有效部分
+        let mut other = List::<u8, 3>::from_slice(&[1, 2]);
+        assert_ne!(list, other);
+        other.push(3);
+        assert_eq!(list, other);
+    }
+}
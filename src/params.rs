@@ -0,0 +1,377 @@
+/// Max number of parameters and sub-parameters kept for a single sequence.
+pub const MAX_PARAMS: usize = 32;
+
+/// Parameters of a CSI or DCS sequence.
+///
+/// A parameter may carry `:` separated sub-parameters, ie `38:2::255:0:0`.
+/// An empty parameter or sub-parameter is kept as `None`, meaning the default
+/// value of the sequence should be used.
+///
+/// ```
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::parser::matches;
+///
+/// let parsed: Vec<_> = matches("\x1b[4:3;38:2::255:0:0m", ParserConfig::default()).collect();
+/// let params = parsed[0].params();
+/// let groups: Vec<_> = params.iter().collect();
+/// assert_eq!(groups[0], &[Some(4), Some(3)]);
+/// assert_eq!(groups[1], &[Some(38), Some(2), None, Some(255), Some(0), Some(0)]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Params {
+    // 参数和子参数
+    params: [Option<u16>; MAX_PARAMS],
+    // 每个参数包含的值的个数
+    groups: [u8; MAX_PARAMS],
+    values_len: usize,
+    // 参数个数
+    groups_len: usize,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            params: [None; MAX_PARAMS],
+            groups: [0; MAX_PARAMS],
+            values_len: 0,
+            groups_len: 0,
+        }
+    }
+}
+
+impl PartialEq for Params {
+    fn eq(&self, other: &Self) -> bool {
+        // 只比较有效部分
+        self.as_slice() == other.as_slice()
+            && self.groups[..self.groups_len] == other.groups[..other.groups_len]
+    }
+}
+
+impl Eq for Params {}
+
+impl Params {
+    /// Number of parameters, not counting sub-parameters.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.groups_len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.groups_len == 0
+    }
+
+    /// All parameters and sub-parameters, in order.
+    #[inline]
+    pub fn as_slice(&self) -> &[Option<u16>] {
+        &self.params[..self.values_len]
+    }
+
+    /// Iterates over the parameters without their sub-parameters.
+    ///
+    /// ```
+    /// use csi_parser::config::ParserConfig;
+    /// use csi_parser::parser::matches;
+    ///
+    /// let parsed: Vec<_> = matches("\x1b[;38:5:1;99999m", ParserConfig::default()).collect();
+    /// let values: Vec<_> = parsed[0].params().values().collect();
+    /// assert_eq!(values, vec![None, Some(38), Some(u16::MAX)]);
+    /// ```
+    #[inline]
+    pub fn values(&self) -> ParamsIter<'_> {
+        ParamsIter {
+            groups: self.iter(),
+        }
+    }
+
+    /// Iterates over the parameters, each one followed by its sub-parameters.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            params: self,
+            pos: 0,
+            group: 0,
+        }
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.values_len == MAX_PARAMS
+    }
+
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.values_len = 0;
+        self.groups_len = 0;
+    }
+
+    /// Appends a parameter, returns `false` if there is no room left.
+    #[inline]
+    pub(crate) fn push(&mut self, param: Option<u16>) -> bool {
+        if self.is_full() {
+            return false;
+        }
+
+        self.params[self.values_len] = param;
+        self.groups[self.groups_len] = 1;
+        self.values_len += 1;
+        self.groups_len += 1;
+        true
+    }
+
+    /// Appends a sub-parameter to the last parameter, returns `false` if
+    /// there is no room left.
+    #[inline]
+    pub(crate) fn push_subparam(&mut self, param: Option<u16>) -> bool {
+        if self.groups_len == 0 {
+            return self.push(param);
+        }
+        if self.is_full() {
+            return false;
+        }
+
+        self.params[self.values_len] = param;
+        self.groups[self.groups_len - 1] += 1;
+        self.values_len += 1;
+        true
+    }
+
+    /// Parses the parameter bytes of a sequence, other bytes such as a
+    /// private marker are skipped. Parameters that don't fit are dropped.
+    pub(crate) fn parse(bytes: &[u8]) -> Self {
+        let mut builder = ParamsBuilder::default();
+        for &byte in bytes {
+            if matches!(byte, b'0'..=b';') {
+                builder.push_byte(byte);
+            }
+        }
+        builder.finish();
+
+        builder.params
+    }
+
+    /// Appends a parameter or a sub-parameter.
+    #[inline]
+    pub(crate) fn push_value(&mut self, subparam: bool, param: Option<u16>) -> bool {
+        if subparam {
+            self.push_subparam(param)
+        } else {
+            self.push(param)
+        }
+    }
+}
+
+/// Iterator over the parameters of [`Params`], see [`Params::iter`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    params: &'a Params,
+    // 下一个参数的起始位置
+    pos: usize,
+    group: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [Option<u16>];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.group == self.params.groups_len {
+            return None;
+        }
+
+        let start = self.pos;
+        self.pos += usize::from(self.params.groups[self.group]);
+        self.group += 1;
+        Some(&self.params.params[start..self.pos])
+    }
+}
+
+/// Iterator over the parameters of [`Params`] without their sub-parameters,
+/// see [`Params::values`].
+#[derive(Debug, Clone)]
+pub struct ParamsIter<'a> {
+    groups: Iter<'a>,
+}
+
+impl Iterator for ParamsIter<'_> {
+    type Item = Option<u16>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.groups.next().map(|group| group[0])
+    }
+}
+
+/// Collects the parameters of a sequence byte by byte.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ParamsBuilder {
+    pub params: Params,
+    param: Option<u16>,
+    // 是否有参数字节
+    has_param: bool,
+    // 当前参数是子参数,即跟在`:`之后
+    subparam: bool,
+    // 有参数被丢弃或饱和
+    oversize: bool,
+}
+
+impl ParamsBuilder {
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.params.clear();
+        self.param = None;
+        self.has_param = false;
+        self.subparam = false;
+        self.oversize = false;
+    }
+
+    /// Whether a parameter has been dropped or saturated since the last
+    /// [`ParamsBuilder::clear`].
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn is_oversize(&self) -> bool {
+        self.oversize
+    }
+
+    /// Feeds a parameter byte (`0–9`, `:` or `;`), returns `false` if a
+    /// parameter has been dropped.
+    #[inline]
+    pub(crate) fn push_byte(&mut self, byte: u8) -> bool {
+        self.has_param = true;
+        if byte.is_ascii_digit() {
+            let value = u32::from(self.param.unwrap_or(0)) * 10 + u32::from(byte - b'0');
+            self.oversize |= value > u32::from(u16::MAX);
+            self.param = push_digit(self.param, byte);
+            return true;
+        }
+
+        let pushed = self.params.push_value(self.subparam, self.param.take());
+        self.subparam = byte == b':';
+        self.oversize |= !pushed;
+        pushed
+    }
+
+    /// Ends the last parameter, returns `false` if it has been dropped.
+    #[inline]
+    pub(crate) fn finish(&mut self) -> bool {
+        if !core::mem::take(&mut self.has_param) {
+            return true;
+        }
+
+        let pushed = self.params.push_value(self.subparam, self.param.take());
+        self.oversize |= !pushed;
+        pushed
+    }
+}
+
+/// Appends a decimal digit to a parameter, saturating at `u16::MAX`.
+#[inline]
+pub(crate) fn push_digit(param: Option<u16>, digit: u8) -> Option<u16> {
+    let digit = u16::from(digit - b'0');
+    Some(param.unwrap_or(0).saturating_mul(10).saturating_add(digit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_params() {
+        let mut params = Params::default();
+        assert!(params.is_empty());
+
+        assert!(params.push(Some(31)));
+        assert!(params.push(None));
+        assert_eq!(params.as_slice(), &[Some(31), None]);
+
+        params.clear();
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn subparams() {
+        let params = Params::parse(b"38:2::255:0:0;;4:3");
+        assert_eq!(params.len(), 3);
+
+        let mut iter = params.iter();
+        assert_eq!(
+            iter.next(),
+            Some(&[Some(38), Some(2), None, Some(255), Some(0), Some(0)][..])
+        );
+        assert_eq!(iter.next(), Some(&[None][..]));
+        assert_eq!(iter.next(), Some(&[Some(4), Some(3)][..]));
+        assert_eq!(iter.next(), None);
+
+        assert!(Params::parse(b"").is_empty());
+        assert_eq!(Params::parse(b"?25").as_slice(), &[Some(25)]);
+    }
+
+    #[test]
+    fn values() {
+        let params = Params::parse(b";38:5:1;99999");
+        let mut values = params.values();
+        assert_eq!(values.next(), Some(None));
+        assert_eq!(values.next(), Some(Some(38)));
+        assert_eq!(values.next(), Some(Some(u16::MAX)));
+        assert_eq!(values.next(), None);
+    }
+
+    #[test]
+    fn builder() {
+        let mut builder = ParamsBuilder::default();
+        for &byte in b"1;2" {
+            assert!(builder.push_byte(byte));
+        }
+        assert!(builder.finish());
+        assert!(builder.finish());
+        assert_eq!(builder.params, Params::parse(b"1;2"));
+
+        builder.clear();
+        assert!(builder.params.is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn oversize_builder() {
+        let mut builder = ParamsBuilder::default();
+        b"65535".iter().for_each(|&byte| {
+            builder.push_byte(byte);
+        });
+        builder.finish();
+        assert!(!builder.is_oversize());
+
+        builder.clear();
+        b"65536".iter().for_each(|&byte| {
+            builder.push_byte(byte);
+        });
+        assert!(builder.is_oversize());
+
+        builder.clear();
+        for _ in 0..MAX_PARAMS {
+            assert!(builder.push_byte(b';'));
+        }
+        assert!(!builder.is_oversize());
+        assert!(!builder.finish());
+        assert!(builder.is_oversize());
+    }
+
+    #[test]
+    fn full_params() {
+        let mut params = Params::default();
+        for _ in 0..MAX_PARAMS {
+            assert!(params.push(Some(1)));
+        }
+
+        assert!(params.is_full());
+        assert!(!params.push(Some(1)));
+        assert_eq!(params.len(), MAX_PARAMS);
+    }
+
+    #[test]
+    fn saturating_digits() {
+        let param = b"99999".iter().fold(None, |p, &d| push_digit(p, d));
+        assert_eq!(param, Some(u16::MAX));
+
+        let param = b"0042".iter().fold(None, |p, &d| push_digit(p, d));
+        assert_eq!(param, Some(42));
+    }
+}
@@ -1,71 +1,510 @@
-#[cfg(all(not(feature = "std"), feature = "no_std"))]
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
 use alloc::vec::Vec;
 
 #[cfg(feature = "std")]
 use std::vec::Vec;
 
-use crate::{CSI, SEPARATOR};
+use crate::config::ParserConfig;
+use crate::enums::CharsetIndex;
+#[cfg(feature = "alloc")]
+use crate::error::{ParseError, ParseErrorKind};
+use crate::params::Params;
+use crate::scanner::{Scanner, Token, TokenKind, C1};
+#[cfg(feature = "alloc")]
+use crate::CSI;
+use crate::{intermediate_byte, SEPARATOR};
+use core::ops::Range;
+
+/// Text that can be parsed, either `str` or `[u8]`.
+///
+/// This trait is sealed.
+pub trait Input: sealed::Sealed {
+    fn as_bytes(&self) -> &[u8];
+
+    /// Sub-slice of the input, the range is always on character boundaries.
+    fn slice(&self, range: Range<usize>) -> &Self;
+}
+
+impl Input for str {
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+
+    #[inline]
+    fn slice(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+}
+
+impl Input for [u8] {
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    #[inline]
+    fn slice(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+}
+
+mod sealed {
+    pub trait Sealed {
+        /// Whether the input is UTF-8, which decides how C1 controls are encoded.
+        const UTF8: bool;
+    }
+
+    impl Sealed for str {
+        const UTF8: bool = true;
+    }
+
+    impl Sealed for [u8] {
+        const UTF8: bool = false;
+    }
+}
 
 /// A match.
 #[derive(Debug, PartialEq, Eq)]
-pub struct Match<'t> {
+pub struct Match<'t, T: ?Sized = str> {
     /// First byte index.
     pub start: usize,
     /// Last byte index + 1.
     pub end: usize,
     /// The text slice (ie `text[start..end]`).
     /// Note that the range is `(start..end]`.
-    pub csi_text: &'t str,
+    pub csi_text: &'t T,
     /// The CSI type (ie `m`, `A` `..`)
     pub csi_type: u8,
+    /// The private marker `<`, `=`, `>` or `?` before the parameters.
+    pub private_marker: Option<u8>,
+    /// The intermediate bytes before the final byte (ie the space in
+    /// `CSI 2 SP q`), including the C0 controls and `DEL` between them.
+    pub intermediates: &'t T,
+    // 扫描时得到的参数
+    params: Params,
 }
 
-impl Match<'_> {
-    /// full define is `pub fn parse_ansi_seq<'a>(&'a self) -> Vec<Output<'a>>`
+impl<'t, T: Input + ?Sized> Match<'t, T> {
+    /// `params` are the ones collected while scanning the sequence.
+    pub(crate) fn new(text: &'t T, token: Token, params: &Params) -> Self {
+        let bytes = text.as_bytes();
+        // 跳过序列中的控制字符和 DEL
+        let private_marker = match bytes[token.data_start..token.data_end]
+            .iter()
+            .find(|&&b| b > 0x1f && b != 0x7f)
+        {
+            Some(&marker @ 0x3c..=0x3f) => Some(marker),
+            _ => None,
+        };
+
+        Match {
+            start: token.start,
+            end: token.end,
+            csi_text: text.slice(token.start..token.end),
+            csi_type: bytes[token.end - 1],
+            private_marker,
+            intermediates: text.slice(intermediates(bytes, token)),
+            params: *params,
+        }
+    }
+
+    /// The parameters, with their `:` separated sub-parameters.
+    ///
+    /// They are collected while scanning, so this doesn't parse `csi_text`
+    /// again.
+    ///
+    /// ```
+    /// use csi_parser::config::ParserConfig;
+    /// use csi_parser::parser::matches;
+    ///
+    /// let parsed: Vec<_> = matches("\x1b[38:2::255:0:0m", ParserConfig::default()).collect();
+    /// let params = parsed[0].params();
+    /// assert_eq!(params.len(), 1);
+    /// assert_eq!(params.as_slice()[..2], [Some(38), Some(2)]);
+    /// ```
+    #[inline]
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// The `;` separated parameters, including a private marker.
+    ///
     /// ```
     /// use csi_parser::parser::parse;
     ///
-    /// let ansi_text = "Hello, \x1b[;31;4mworld\x1b[0m\x1b[10;20Hm!\x1b[m\x1b?25h";
-    /// let parsed: Vec<_> = parse(ansi_text);
-    /// for result in parsed {
-    ///    println!("{:?}", result.parse_csi());
-    /// }
+    /// let ansi_text = "Hello, \x1b[;31;4mworld\x1b[0m\x1b[10;20Hm!\x1b[m\x1b[?25h";
+    /// let parsed: Vec<_> = parse(ansi_text).iter().map(|m| m.parse_csi()).collect();
+    /// assert_eq!(
+    ///     parsed,
+    ///     vec![
+    ///         vec!["", "31", "4"],
+    ///         vec!["0"],
+    ///         vec!["10", "20"],
+    ///         vec![""],
+    ///         vec!["?25"],
+    ///     ]
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn parse_csi(&self) -> Vec<&'t T> {
+        let bytes = self.csi_text.as_bytes();
+        // 参数部分,不包含`CSI`和结束字节
+        let end = bytes.len() - 1;
+
+        let mut params = Vec::new();
+        // 引导符长度: 8-bit CSI 为 1 字节, UTF-8 编码的 C1 CSI 为 2 字节,
+        // `ESC [` 为 2 字节
+        let mut start = match bytes {
+            [0x9b, ..] => 1,
+            [0xc2, 0x9b, ..] => 2,
+            _ => CSI.len(),
+        };
+        for (index, &byte) in bytes.iter().enumerate().take(end).skip(start) {
+            if byte == SEPARATOR {
+                params.push(self.csi_text.slice(start..index));
+                start = index + 1;
+            }
+        }
+        params.push(self.csi_text.slice(start..end));
+
+        params
+    }
+}
+
+/// An escape sequence that is not a control string or CSI, ie `ESC 7` or
+/// `ESC ( 0`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EscMatch<'t, T: ?Sized = str> {
+    /// First byte index.
+    pub start: usize,
+    /// Last byte index + 1.
+    pub end: usize,
+    /// The intermediate bytes before the final byte (ie `(`), including the
+    /// C0 controls executed inside the sequence.
+    pub intermediates: &'t T,
+    /// The final byte (ie `0`).
+    pub esc_type: u8,
+}
+
+impl<T: ?Sized> Clone for EscMatch<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for EscMatch<'_, T> {}
+
+impl<'t, T: Input + ?Sized> EscMatch<'t, T> {
+    pub(crate) fn new(text: &'t T, token: Token) -> Self {
+        EscMatch {
+            start: token.start,
+            end: token.end,
+            intermediates: text.slice(token.data_start..token.data_end),
+            esc_type: text.as_bytes()[token.end - 1],
+        }
+    }
+}
+
+/// A single shift and the character it applies to, ie `ESC O P`, which is
+/// the `F1` key in application keypad mode.
+///
+/// ```
+/// use csi_parser::enums::CharsetIndex;
+/// use csi_parser::iter::{CsiParser, Output};
+///
+/// let out: Vec<_> = "\x1bOA".csi_parser().collect();
+/// let Output::SingleShift(shift) = out[0] else { panic!() };
+/// assert_eq!((shift.charset, shift.character), (CharsetIndex::G3, b'A'));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SingleShift {
+    /// First byte index.
+    pub start: usize,
+    /// Last byte index + 1, after the character.
+    pub end: usize,
+    /// `G2` for `SS2` (`ESC N`), `G3` for `SS3` (`ESC O`).
+    pub charset: CharsetIndex,
+    pub character: u8,
+}
+
+impl SingleShift {
+    pub(crate) fn new(bytes: &[u8], token: Token) -> Self {
+        // 引导符的最后一个字节
+        let charset = match bytes[token.data_start - 1] {
+            b'N' | 0x8e => CharsetIndex::G2,
+            _ => CharsetIndex::G3,
+        };
+
+        SingleShift {
+            start: token.start,
+            end: token.end,
+            charset,
+            character: bytes[token.data_start],
+        }
+    }
+}
+
+/// How a control string (OSC, DCS, ...) was terminated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StringTerminator {
+    /// `BEL` (`0x07`), only accepted for OSC.
+    Bel,
+    /// `ESC \` or the 8-bit `ST` (`0x9C`).
+    St,
+}
+
+/// An OSC (Operating System Command) sequence, ie `ESC ] 0;title BEL`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OscMatch<'t, T: ?Sized = str> {
+    /// First byte index.
+    pub start: usize,
+    /// Last byte index + 1, after the terminator.
+    pub end: usize,
+    /// Everything between the introducer and the terminator (ie `0;title`).
+    pub payload: &'t T,
+    pub terminator: StringTerminator,
+}
+
+impl<T: ?Sized> Clone for OscMatch<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for OscMatch<'_, T> {}
+
+impl<'t, T: Input + ?Sized> OscMatch<'t, T> {
+    pub(crate) fn new(text: &'t T, token: Token, terminator: StringTerminator) -> Self {
+        OscMatch {
+            start: token.start,
+            end: token.end,
+            payload: text.slice(token.data_start..token.data_end),
+            terminator,
+        }
+    }
+
+    /// The numeric command before the first `;`, ie `0` for a window title
+    /// or `8` for a hyperlink.
+    ///
+    /// ```
+    /// use csi_parser::iter::{CsiParser, Output};
     ///
-    /// // output is:
-    /// // ["", "31", "4"]
-    /// // ["0"]
-    /// // ["10", "20"]
-    /// // [""]
-    /// // ["?25"]
+    /// let out: Vec<_> = "\x1b]8;id=1;https://a.b\x1b\\".csi_parser().collect();
+    /// let Output::Osc(osc) = out[0] else { panic!() };
+    /// assert_eq!(osc.command(), Some(8));
+    /// assert_eq!(osc.params().collect::<Vec<_>>(), vec!["id=1", "https://a.b"]);
     /// ```
-    pub fn parse_csi(&self) -> Vec<&str> {
-        // 所有的参数
-        let mut params: Vec<&str> = self.csi_text[CSI.len()..].split(SEPARATOR).collect();
-
-        // 有参数,则证明存在`;`,是多个参数的情况
-        if !params.is_empty() {
-            let last = params.pop();
-            unsafe {
-                let last_param = last.unwrap_unchecked();
-                params.push(&last_param[0..(last_param.len() - 1)]);
+    pub fn command(&self) -> Option<u16> {
+        let bytes = self.payload.as_bytes();
+        let end = bytes
+            .iter()
+            .position(|&b| b == SEPARATOR)
+            .unwrap_or(bytes.len());
+
+        core::str::from_utf8(&bytes[..end]).ok()?.parse().ok()
+    }
+
+    /// The `;` separated parameters after the command.
+    pub fn params(&self) -> OscParams<'t, T> {
+        let bytes = self.payload.as_bytes();
+        match bytes.iter().position(|&b| b == SEPARATOR) {
+            Some(index) => OscParams {
+                payload: self.payload,
+                pos: Some(index + 1),
+            },
+            None => OscParams {
+                payload: self.payload,
+                pos: None,
+            },
+        }
+    }
+}
+
+/// Iterator over the parameters of an [`OscMatch`].
+#[derive(Debug)]
+pub struct OscParams<'t, T: ?Sized = str> {
+    payload: &'t T,
+    // 下一个参数的起始位置
+    pos: Option<usize>,
+}
+
+impl<'t, T: Input + ?Sized> Iterator for OscParams<'t, T> {
+    type Item = &'t T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos?;
+        let bytes = &self.payload.as_bytes()[start..];
+
+        match bytes.iter().position(|&b| b == SEPARATOR) {
+            Some(index) => {
+                self.pos = Some(start + index + 1);
+                Some(self.payload.slice(start..start + index))
+            }
+            None => {
+                self.pos = None;
+                Some(self.payload.slice(start..start + bytes.len()))
             }
-            return params;
         }
+    }
+}
+
+/// The introducer of a [`StringMatch`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StringKind {
+    /// Start of String, `ESC X`.
+    Sos,
+    /// Privacy Message, `ESC ^`.
+    Pm,
+    /// Application Program Command, `ESC _`.
+    Apc,
+}
+
+/// A SOS, PM or APC string, ie the Kitty graphics command
+/// `ESC _ Gf=24;AAAA ESC \`.
+///
+/// These strings are only terminated by `ST`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StringMatch<'t, T: ?Sized = str> {
+    /// First byte index.
+    pub start: usize,
+    /// Last byte index + 1, after the terminator.
+    pub end: usize,
+    pub kind: StringKind,
+    /// Everything between the introducer and the terminator, borrowed from
+    /// the input.
+    pub payload: &'t T,
+}
+
+impl<T: ?Sized> Clone for StringMatch<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for StringMatch<'_, T> {}
+
+impl<'t, T: Input + ?Sized> StringMatch<'t, T> {
+    pub(crate) fn new(text: &'t T, token: Token) -> Self {
+        // 引导符的最后一个字节
+        let kind = match text.as_bytes()[token.data_start - 1] {
+            b'X' | 0x98 => StringKind::Sos,
+            b'^' | 0x9e => StringKind::Pm,
+            _ => StringKind::Apc,
+        };
 
-        // 否则是单个参数
-        if self.csi_text.len() > CSI.len() + 1 {
-            params.push(&self.csi_text[CSI.len()..(self.end - 1)]);
-        } else {
-            // 不存在参数,用0填充
-            params.push("0");
+        StringMatch {
+            start: token.start,
+            end: token.end,
+            kind,
+            payload: text.slice(token.data_start..token.data_end),
         }
+    }
+}
+
+/// The header of a DCS (Device Control String) sequence, ie `ESC P 1;2 $ q`.
+///
+/// The data string that follows is yielded in parts, see
+/// [`Output::DcsPut`](crate::iter::Output::DcsPut).
+#[derive(Debug, PartialEq, Eq)]
+pub struct DcsMatch<'t, T: ?Sized = str> {
+    /// First byte index.
+    pub start: usize,
+    /// Last byte index + 1, after the final byte.
+    pub end: usize,
+    /// The parameter bytes, including a private marker (ie `1;2`), parsed by
+    /// [`DcsMatch::params`].
+    pub raw_params: &'t T,
+    /// The intermediate bytes before the final byte (ie `$`).
+    pub intermediates: &'t T,
+    /// The final byte (ie `q`).
+    pub dcs_type: u8,
+}
+
+impl<T: ?Sized> Clone for DcsMatch<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for DcsMatch<'_, T> {}
+
+impl<'t, T: Input + ?Sized> DcsMatch<'t, T> {
+    pub(crate) fn new(text: &'t T, token: Token) -> Self {
+        let intermediates = intermediates(text.as_bytes(), token);
+
+        DcsMatch {
+            start: token.start,
+            end: token.end,
+            raw_params: text.slice(token.data_start..intermediates.start),
+            intermediates: text.slice(intermediates),
+            dcs_type: text.as_bytes()[token.end - 1],
+        }
+    }
+
+    /// The parameters, with their `:` separated sub-parameters.
+    pub fn params(&self) -> Params {
+        Params::parse(self.raw_params.as_bytes())
+    }
+
+    /// The `;` separated parameters.
+    ///
+    /// ```
+    /// use csi_parser::iter::{CsiParser, Output};
+    ///
+    /// let out: Vec<_> = "\x1bP1;2$qm\x1b\\".csi_parser().collect();
+    /// let Output::DcsHook(dcs) = out[0] else { panic!() };
+    /// assert_eq!(dcs.parse_params(), vec!["1", "2"]);
+    /// assert_eq!((dcs.intermediates, dcs.dcs_type), ("$", b'q'));
+    /// assert_eq!(out[1], Output::DcsPut("m"));
+    /// assert_eq!(out[2], Output::DcsUnhook);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn parse_params(&self) -> Vec<&'t T> {
+        let bytes = self.raw_params.as_bytes();
+
+        let mut params = Vec::new();
+        let mut start = 0;
+        for (index, &byte) in bytes.iter().enumerate() {
+            if byte == SEPARATOR {
+                params.push(self.raw_params.slice(start..index));
+                start = index + 1;
+            }
+        }
+        params.push(self.raw_params.slice(start..bytes.len()));
 
         params
     }
 }
 
+/// Range of the intermediate bytes at the end of the data of `token`, with
+/// the C0 controls and `DEL` among them but not the ones around them.
+fn intermediates(bytes: &[u8], token: Token) -> Range<usize> {
+    let data = &bytes[token.data_start..token.data_end];
+    // 结尾的中间字节、控制字符和 DEL
+    let run = data
+        .iter()
+        .rposition(|&b| b > 0x2f && b != 0x7f)
+        .map_or(0, |index| index + 1);
+    let tail = &data[run..];
+    match tail.iter().position(|&b| intermediate_byte(b)) {
+        Some(first) => {
+            let last = tail
+                .iter()
+                .rposition(|&b| intermediate_byte(b))
+                .unwrap_or(first);
+            token.data_start + run + first..token.data_start + run + last + 1
+        }
+        None => token.data_end..token.data_end,
+    }
+}
+
 /// Parses CSI escape codes from the given text, returning a vector of `Match`.
 ///
+/// The text is fed through the VT500-series state machine, so C0 controls
+/// inside a sequence are skipped, `CAN`/`SUB` abort it and `ESC` restarts it.
+/// Only the dispatched CSI sequences are returned.
+///
 /// ```rust
 /// use csi_parser::parser::parse;
 /// let ansi_text = "Hello, \x1b[31;4mworld\x1b[0m!";
@@ -78,66 +517,182 @@ impl Match<'_> {
 ///     vec![(7, 14), (19, 23)],
 /// );
 /// ```
-pub fn parse(text: &str) -> Vec<Match> {
-    let mut v = Vec::with_capacity(8);
-    let csi_len = CSI.len();
-
-    let mut s = text;
-    let mut start = 0;
-    let mut end = start + csi_len;
-
-    while end <= text.len() {
-        // start of a CSI seq
-        if s.starts_with(CSI) {
-            let mut byte = text.as_bytes()[end];
-
-            // get end or CSI seq
-            while end < text.len()
-                // 必须是参数字节或者是中间字节
-                && (crate::parameter_byte(byte) || (crate::intermediate_byte(byte)))
-            {
-                // 更新end
-                end += 1;
-                // 更新byte
-                byte = text.as_bytes()[end];
-            }
+#[cfg(feature = "alloc")]
+pub fn parse(text: &str) -> Vec<Match<'_>> {
+    parse_with(text, ParserConfig::default())
+}
 
-            // 紧跟着的不是终结字符
-            byte = text.as_bytes()[end];
-            if !crate::terminated_byte(byte) {
-                break;
-            }
+/// Same as [`parse`], with the given config.
+#[cfg(feature = "alloc")]
+pub fn parse_with(text: &str, config: ParserConfig) -> Vec<Match<'_>> {
+    parse_input(text, config)
+}
 
-            // 越界检查,获取CSI的切片
-            let end = end + 1;
-            if end > text.len() {
-                break;
-            }
+/// Parses CSI escape codes from bytes, which don't have to be valid UTF-8.
+///
+/// ```rust
+/// use csi_parser::parser::parse_bytes;
+/// let parsed = parse_bytes(b"\xff\x1b[1m\xe4\x1b[0m");
+/// assert_eq!(parsed[0].csi_text, b"\x1b[1m");
+/// assert_eq!((parsed[1].start, parsed[1].end), (6, 10));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn parse_bytes(bytes: &[u8]) -> Vec<Match<'_, [u8]>> {
+    parse_bytes_with(bytes, ParserConfig::default())
+}
+
+/// Same as [`parse_bytes`], with the given config.
+///
+/// ```rust
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::parser::parse_bytes_with;
+///
+/// let config = ParserConfig {
+///     c1_controls: true,
+///     ..ParserConfig::default()
+/// };
+/// let parsed = parse_bytes_with(b"\x9b31m\x9d0;title\x9c\x9b0m", config);
+/// assert_eq!(parsed[0].csi_text, b"\x9b31m");
+/// assert_eq!(parsed[1].csi_text, b"\x9b0m");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn parse_bytes_with(bytes: &[u8], config: ParserConfig) -> Vec<Match<'_, [u8]>> {
+    parse_input(bytes, config)
+}
 
-            v.push(Match {
-                start,
-                end,
-                csi_text: &text[start..end],
-                csi_type: unsafe { (text[(end - 1)..end]).chars().next().unwrap_unchecked() as u8 },
-            });
+#[cfg(feature = "alloc")]
+pub(crate) fn parse_input<T: Input + ?Sized>(text: &T, config: ParserConfig) -> Vec<Match<'_, T>> {
+    matches(text, config).collect()
+}
+
+/// Iterates over the CSI sequences of `text`, without allocating.
+///
+/// ```rust
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::parser::matches;
+///
+/// let mut iter = matches("a\x1b[1mb\x1b[0m", ParserConfig::default());
+/// assert_eq!(iter.next().map(|m| m.csi_text), Some("\x1b[1m"));
+/// assert_eq!(iter.next().map(|m| m.csi_text), Some("\x1b[0m"));
+/// assert!(iter.next().is_none());
+///
+/// let mut iter = matches(&b"\xff\x1b[2J"[..], ParserConfig::default());
+/// assert_eq!(iter.next().map(|m| m.start), Some(1));
+/// ```
+pub fn matches<T: Input + ?Sized>(text: &T, config: ParserConfig) -> Matches<'_, T> {
+    let scanner = Scanner::new(text.as_bytes(), C1::new::<T>(config)).limits(config);
+    Matches { text, scanner }
+}
+
+/// Iterator over the CSI sequences of a text, see [`matches()`].
+pub struct Matches<'t, T: ?Sized = str> {
+    text: &'t T,
+    scanner: Scanner<'t>,
+}
 
-            start = end;
-        } else {
-            start += s.chars().next().expect("non-empty-str").len_utf8();
+impl<'t, T: Input + ?Sized> Iterator for Matches<'t, T> {
+    type Item = Match<'t, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let token = self.scanner.next()?;
+            if token.kind == TokenKind::Csi {
+                return Some(Match::new(self.text, token, self.scanner.params()));
+            }
         }
+    }
+}
 
-        s = &text[start..];
-        end = start + csi_len;
+/// Same as [`parse_with`], also reporting malformed sequences.
+///
+/// Malformed sequences are skipped as usual, the matches are the same as
+/// the ones of [`parse_with`]. A CSI sequence with oversize parameters is
+/// both matched and reported.
+///
+/// ```rust
+/// use csi_parser::config::ParserConfig;
+/// use csi_parser::error::{ParseError, ParseErrorKind};
+/// use csi_parser::parser::parse_with_diagnostics;
+///
+/// let (parsed, errors) = parse_with_diagnostics("\x1b[1<2m\x1b[1m\x1b[", ParserConfig::default());
+/// assert_eq!(parsed[0].csi_text, "\x1b[1m");
+/// assert_eq!(
+///     errors,
+///     vec![
+///         ParseError { kind: ParseErrorKind::InvalidFinalByte, start: 0, end: 6 },
+///         ParseError { kind: ParseErrorKind::TruncatedSequence, start: 10, end: 12 },
+///     ],
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn parse_with_diagnostics(
+    text: &str,
+    config: ParserConfig,
+) -> (Vec<Match<'_>>, Vec<ParseError>) {
+    parse_input_with_diagnostics(text, config)
+}
+
+/// Same as [`parse_bytes_with`], also reporting malformed sequences, see
+/// [`parse_with_diagnostics`].
+#[cfg(feature = "alloc")]
+pub fn parse_bytes_with_diagnostics(
+    bytes: &[u8],
+    config: ParserConfig,
+) -> (Vec<Match<'_, [u8]>>, Vec<ParseError>) {
+    parse_input_with_diagnostics(bytes, config)
+}
+
+#[cfg(feature = "alloc")]
+fn parse_input_with_diagnostics<T: Input + ?Sized>(
+    text: &T,
+    config: ParserConfig,
+) -> (Vec<Match<'_, T>>, Vec<ParseError>) {
+    let mut v = Vec::with_capacity(8);
+    let mut errors = Vec::new();
+
+    let mut scanner = Scanner::new(text.as_bytes(), C1::new::<T>(config))
+        .limits(config)
+        .report_errors();
+    while let Some(token) = scanner.next() {
+        match token.kind {
+            TokenKind::Csi => {
+                if scanner.params_oversize() {
+                    errors.push(ParseError {
+                        kind: ParseErrorKind::OversizeParams,
+                        start: token.start,
+                        end: token.end,
+                    });
+                }
+                v.push(Match::new(text, token, scanner.params()));
+            }
+            TokenKind::Malformed(kind) => errors.push(ParseError {
+                kind,
+                start: token.start,
+                end: token.end,
+            }),
+            TokenKind::DcsUnhook { truncated: true } => errors.push(ParseError {
+                kind: ParseErrorKind::TruncatedSequence,
+                start: token.start,
+                end: token.end,
+            }),
+            _ => {}
+        }
     }
 
-    v
+    (v, errors)
 }
 
-#[cfg(test)]
+// 测试用例需要分配内存
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
-    #[cfg(all(not(feature = "std"), feature = "no_std"))]
-    use alloc::vec;
+    use crate::enums::{CSISequence, DecMode, Modes};
+    use crate::iter::{CsiParser, Output};
+    use crate::params::MAX_PARAMS;
+    use crate::perform::{Parser as PerformParser, Perform};
+    use crate::stream::StreamParser;
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
+    use alloc::{format, string::String, vec};
 
     #[test]
     fn parse_test() {
@@ -151,12 +706,18 @@ mod tests {
                     end: 14,
                     csi_text: "\x1b[31;4m",
                     csi_type: b'm',
+                    private_marker: None,
+                    intermediates: "",
+                    params: Params::parse(b"31;4"),
                 },
                 Match {
                     start: 19,
                     end: 23,
                     csi_text: "\x1b[0m",
                     csi_type: b'm',
+                    private_marker: None,
+                    intermediates: "",
+                    params: Params::parse(b"0"),
                 },
             ]
         );
@@ -174,12 +735,18 @@ mod tests {
                     end: 13,
                     csi_text: "\x1b[31;4m",
                     csi_type: b'm',
+                    private_marker: None,
+                    intermediates: "",
+                    params: Params::parse(b"31;4"),
                 },
                 Match {
                     start: 17,
                     end: 21,
                     csi_text: "\x1b[0m",
                     csi_type: b'm',
+                    private_marker: None,
+                    intermediates: "",
+                    params: Params::parse(b"0"),
                 },
             ]
         );
@@ -196,6 +763,9 @@ mod tests {
                 end: 5,
                 csi_text: "\x1b[31A",
                 csi_type: b'A',
+                private_marker: None,
+                intermediates: "",
+                params: Params::parse(b"31"),
             },]
         );
     }
@@ -218,7 +788,329 @@ mod tests {
                 end: 8,
                 csi_text: "\x1b[0m",
                 csi_type: b'm',
+                private_marker: None,
+                intermediates: "",
+                params: Params::parse(b"0"),
             },]
         );
     }
+
+    #[test]
+    fn matches_without_alloc() {
+        let text = "\x1b[1;31mred\x1b[0m \x1b[?25l\x1b[1<2m\x1b[2J";
+        for config in [
+            ParserConfig::default(),
+            ParserConfig {
+                max_params: Some(1),
+                ..ParserConfig::default()
+            },
+        ] {
+            let iter: Vec<_> = matches(text, config).collect();
+            assert_eq!(iter, parse_with(text, config));
+        }
+    }
+
+    #[test]
+    fn truncated_escape() {
+        assert_eq!(parse("oops\x1b[31"), vec![]);
+        assert_eq!(parse("oops\x1b["), vec![]);
+        assert_eq!(parse("oops\x1b"), vec![]);
+    }
+
+    #[test]
+    fn escape_restarts_sequence() {
+        let x = parse("\x1b[31\x1b[4m");
+
+        assert_eq!(
+            x,
+            vec![Match {
+                start: 4,
+                end: 8,
+                csi_text: "\x1b[4m",
+                csi_type: b'm',
+                private_marker: None,
+                intermediates: "",
+                params: Params::parse(b"4"),
+            },]
+        );
+    }
+
+    #[test]
+    fn cancel_aborts_sequence() {
+        let x = parse("\x1b[31\x18m\x1b[0m");
+
+        assert_eq!(
+            x,
+            vec![Match {
+                start: 6,
+                end: 10,
+                csi_text: "\x1b[0m",
+                csi_type: b'm',
+                private_marker: None,
+                intermediates: "",
+                params: Params::parse(b"0"),
+            },]
+        );
+    }
+
+    #[test]
+    fn skip_other_sequences() {
+        let x = parse("\x1b]0;title\x07\x1b7\x1b[?25h\x1bPq#0\x1b\\");
+
+        assert_eq!(
+            x,
+            vec![Match {
+                start: 12,
+                end: 18,
+                csi_text: "\x1b[?25h",
+                csi_type: b'h',
+                private_marker: Some(b'?'),
+                intermediates: "",
+                params: Params::parse(b"?25"),
+            },]
+        );
+    }
+
+    #[test]
+    fn parse_after_malformed() {
+        let x = parse("\x1b[1<2m\x1b[0m");
+
+        assert_eq!(
+            x,
+            vec![Match {
+                start: 6,
+                end: 10,
+                csi_text: "\x1b[0m",
+                csi_type: b'm',
+                private_marker: None,
+                intermediates: "",
+                params: Params::parse(b"0"),
+            },]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_utf8() {
+        let x = parse_bytes(b"\xc3\x1b[1;\xff2m\x1b[2J\x80");
+
+        assert_eq!(
+            x,
+            vec![Match {
+                start: 8,
+                end: 12,
+                csi_text: &b"\x1b[2J"[..],
+                csi_type: b'J',
+                private_marker: None,
+                intermediates: &b""[..],
+                params: Params::parse(b"2"),
+            },]
+        );
+        assert_eq!(x[0].parse_csi(), vec![&b"2"[..]]);
+    }
+
+    #[test]
+    fn controls_inside_sequence() {
+        let x = parse("\x1b[2 \x07q\x1b[\x07?25h\x1b[1\x07m\x1b[2\x07 \x07 \x07q");
+
+        assert_eq!(x[0].intermediates, " ");
+        assert_eq!(x[0].csi_type, b'q');
+        assert_eq!(x[1].private_marker, Some(b'?'));
+        assert_eq!(x[1].params().as_slice(), &[Some(25)]);
+        assert_eq!(x[2].intermediates, "");
+        assert_eq!(x[3].intermediates, " \x07 ");
+
+        // DEL 和控制字符一样被忽略
+        let x = parse("\x1b[\x7f?25h\x1b[2 \x7fq");
+        assert_eq!(x[0].private_marker, Some(b'?'));
+        assert_eq!(
+            CSISequence::from(&x[0]),
+            CSISequence::DecSet(Modes::from_slice(&[DecMode::ShowCursor]))
+        );
+        assert_eq!(x[1].intermediates, " ");
+
+        let dcs = matches!(
+            "\x1bP1\x07$\x07qx\x1b\\".csi_parser().next(),
+            Some(Output::DcsHook(DcsMatch {
+                raw_params: "1\x07",
+                intermediates: "$",
+                ..
+            }))
+        );
+        assert!(dcs);
+    }
+
+    #[test]
+    fn parse_c1_controls() {
+        let config = ParserConfig {
+            c1_controls: true,
+            ..ParserConfig::default()
+        };
+        let text = "a\u{9b}31mb\u{9d}0;t\u{9c}\x1b[0m";
+        let x = parse_with(text, config);
+
+        assert_eq!(
+            x,
+            vec![
+                Match {
+                    start: 1,
+                    end: 6,
+                    csi_text: "\u{9b}31m",
+                    csi_type: b'm',
+                    private_marker: None,
+                    intermediates: "",
+                    params: Params::parse(b"31"),
+                },
+                Match {
+                    start: 14,
+                    end: 18,
+                    csi_text: "\x1b[0m",
+                    csi_type: b'm',
+                    private_marker: None,
+                    intermediates: "",
+                    params: Params::parse(b"0"),
+                },
+            ]
+        );
+        assert_eq!(x[0].parse_csi(), vec!["31"]);
+        assert_eq!(parse(text).len(), 1);
+
+        let x = parse_bytes_with(b"\xe4\x9b2J", config);
+        assert_eq!(x[0].csi_text, b"\x9b2J");
+        assert_eq!(x[0].parse_csi(), vec![b"2"]);
+        assert!(parse_bytes(b"\xe4\x9b2J").is_empty());
+    }
+
+    fn errors(text: &str) -> Vec<(ParseErrorKind, usize, usize)> {
+        let config = ParserConfig {
+            c1_controls: true,
+            ..ParserConfig::default()
+        };
+        let (matches, errors) = parse_with_diagnostics(text, config);
+        assert_eq!(matches, parse_with(text, config));
+
+        errors.iter().map(|e| (e.kind, e.start, e.end)).collect()
+    }
+
+    #[test]
+    fn diagnostics() {
+        use ParseErrorKind::*;
+
+        assert!(errors("a\x1b[1mb\x1b]0;t\x07\x1bPq#0\x1b\\\x1b7").is_empty());
+        assert_eq!(errors("\x1b[1<2m"), vec![(InvalidFinalByte, 0, 6)]);
+        assert_eq!(errors("a\x1b[12"), vec![(TruncatedSequence, 1, 5)]);
+        assert_eq!(errors("\x1b[1\x18m"), vec![(TruncatedSequence, 0, 3)]);
+        assert_eq!(errors("\x1b[1\x1b[2m"), vec![(TruncatedSequence, 0, 3)]);
+        assert_eq!(errors("\x1b[1\u{9b}2m"), vec![(TruncatedSequence, 0, 3)]);
+        assert_eq!(errors("\x1b]0;t\x1b[1m"), vec![(TruncatedSequence, 0, 5)]);
+        assert_eq!(errors("\x1b]0;t"), vec![(TruncatedSequence, 0, 5)]);
+        assert_eq!(errors("\x1bP1<q\x1b\\"), vec![(InvalidFinalByte, 0, 7)]);
+        // 未结束的 DCS
+        assert_eq!(errors("a\x1bPqabc"), vec![(TruncatedSequence, 1, 7)]);
+        assert_eq!(errors("\x1bPq"), vec![(TruncatedSequence, 0, 3)]);
+        assert_eq!(errors("\x1bP1$"), vec![(TruncatedSequence, 0, 4)]);
+        assert_eq!(
+            errors("\x1bP1$r0m\x1b"),
+            vec![(TruncatedSequence, 0, 7), (TruncatedSequence, 7, 8)]
+        );
+        assert_eq!(errors("\x1b[99999m"), vec![(OversizeParams, 0, 8)]);
+
+        let params = ";".repeat(MAX_PARAMS);
+        let text = format!("\x1b[{}m", params);
+        assert_eq!(errors(&text), vec![(OversizeParams, 0, text.len())]);
+    }
+
+    #[test]
+    fn limits() {
+        let config = ParserConfig {
+            max_params: Some(2),
+            max_param_value: Some(1000),
+            max_sequence_len: Some(8),
+            max_string_len: Some(4),
+            ..ParserConfig::default()
+        };
+        let text = "\x1b[1;2;3m\x1b[1001m\x1b[123456789m\x1b]0;title\x07\x1bP1;2;3q\x1b\\\x1b[1;2m";
+        let (matches, errors) = parse_with_diagnostics(text, config);
+
+        assert_eq!(matches, parse_with(text, config));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].csi_text, "\x1b[1;2m");
+        let spans: Vec<_> = errors.iter().map(|e| (e.kind, e.start, e.end)).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (ParseErrorKind::LimitExceeded, 0, 8),
+                (ParseErrorKind::LimitExceeded, 8, 15),
+                (ParseErrorKind::LimitExceeded, 15, 27),
+                (ParseErrorKind::LimitExceeded, 27, 37),
+                (ParseErrorKind::LimitExceeded, 37, 47),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_panic() {
+        const BYTES: &[u8] = &[
+            0x1b, b'[', b']', b'P', b'_', b'\\', b'1', b';', b':', b'm', b'?', b' ', 0x18, 0x07,
+            0x9b, 0x9c, 0xc2,
+        ];
+        let configs = [
+            ParserConfig::default(),
+            ParserConfig {
+                c1_controls: true,
+                controls_in_text: true,
+                ..ParserConfig::default()
+            },
+            ParserConfig {
+                c1_controls: true,
+                max_params: Some(1),
+                max_param_value: Some(1),
+                max_sequence_len: Some(3),
+                max_string_len: Some(1),
+                ..ParserConfig::default()
+            },
+        ];
+
+        let mut input = Vec::new();
+        let mut indices = [0; 4];
+        for len in 0..=indices.len() {
+            indices = [0; 4];
+            loop {
+                input.clear();
+                input.extend(indices[..len].iter().map(|&i| BYTES[i]));
+                let text = String::from_utf8_lossy(&input);
+
+                for config in configs {
+                    let (_, errors) = parse_bytes_with_diagnostics(&input, config);
+                    assert!(errors
+                        .iter()
+                        .all(|e| e.start < e.end && e.end <= input.len()));
+                    parse_with_diagnostics(&text, config);
+
+                    input.csi_parser_with(config).for_each(drop);
+                    text.csi_parser_with(config).for_each(drop);
+
+                    let mut stream = StreamParser::with_config(config);
+                    for byte in &input {
+                        stream.feed(core::slice::from_ref(byte), |_| {});
+                    }
+                    stream.flush(|_| {});
+
+                    struct Noop;
+                    impl Perform for Noop {}
+                    PerformParser::with_config(config).advance(&mut Noop, &input);
+                }
+
+                // 下一个组合
+                let mut i = 0;
+                while i < len && indices[i] == BYTES.len() - 1 {
+                    indices[i] = 0;
+                    i += 1;
+                }
+                if i == len {
+                    break;
+                }
+                indices[i] += 1;
+            }
+        }
+    }
 }
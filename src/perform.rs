@@ -0,0 +1,661 @@
+//! A callback driven parser.
+//!
+//! Instead of collecting `Match`es, [`Parser`] feeds every byte through the
+//! state machine and calls the [`Perform`] methods as soon as an action is
+//! complete.
+//!
+//! ```
+//! use csi_parser::params::Params;
+//! use csi_parser::perform::{Parser, Perform};
+//!
+//! #[derive(Default)]
+//! struct Log {
+//!     text: String,
+//!     csi: Vec<(Vec<Option<u16>>, char)>,
+//! }
+//!
+//! impl Perform for Log {
+//!     fn print(&mut self, c: char) {
+//!         self.text.push(c);
+//!     }
+//!
+//!     fn csi_dispatch(&mut self, params: &Params, _: &[u8], _: bool, action: char) {
+//!         self.csi.push((params.as_slice().to_vec(), action));
+//!     }
+//! }
+//!
+//! let mut parser = Parser::new();
+//! let mut log = Log::default();
+//! parser.advance(&mut log, "\x1b[31;mhello\x1b[0m".as_bytes());
+//!
+//! assert_eq!(log.text, "hello");
+//! assert_eq!(log.csi, vec![(vec![Some(31), None], 'm'), (vec![Some(0)], 'm')]);
+//! ```
+
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use crate::config::ParserConfig;
+use crate::params::{Params, ParamsBuilder};
+use crate::state::{Action, State};
+
+/// Max number of intermediate bytes kept for a single sequence.
+const MAX_INTERMEDIATES: usize = 2;
+
+/// Max number of `;` separated OSC parameters.
+const MAX_OSC_PARAMS: usize = 16;
+
+/// Max length of an OSC payload without the `alloc` feature, the rest of a
+/// longer payload is dropped.
+#[cfg(not(feature = "alloc"))]
+const MAX_OSC_RAW: usize = 1024;
+
+#[cfg(feature = "alloc")]
+type OscRaw = Vec<u8>;
+
+#[cfg(not(feature = "alloc"))]
+type OscRaw = OscBuf;
+
+/// Fixed-size storage of an OSC payload.
+#[cfg(not(feature = "alloc"))]
+#[derive(Debug)]
+struct OscBuf {
+    bytes: [u8; MAX_OSC_RAW],
+    len: usize,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl Default for OscBuf {
+    fn default() -> Self {
+        OscBuf {
+            bytes: [0; MAX_OSC_RAW],
+            len: 0,
+        }
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl OscBuf {
+    #[inline]
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        // 超出部分丢弃
+        if self.len < MAX_OSC_RAW {
+            self.bytes[self.len] = byte;
+            self.len += 1;
+        }
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl core::ops::Deref for OscBuf {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Performs the actions requested by the [`Parser`].
+///
+/// All methods do nothing by default.
+pub trait Perform {
+    /// Draws a character.
+    fn print(&mut self, _c: char) {}
+
+    /// Executes a C0 control, or a C1 control if they are enabled.
+    fn execute(&mut self, _byte: u8) {}
+
+    /// A DCS sequence is starting, `action` is the final byte of its header.
+    ///
+    /// The data string is passed to [`Perform::put`] byte by byte, and
+    /// [`Perform::unhook`] is called once it ends.
+    fn hook(&mut self, _params: &Params, _intermediates: &[u8], _ignore: bool, _action: char) {}
+
+    /// One byte of the DCS data string.
+    fn put(&mut self, _byte: u8) {}
+
+    /// The DCS data string has ended.
+    fn unhook(&mut self) {}
+
+    /// A complete OSC sequence, split on `;`.
+    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
+
+    /// A complete CSI sequence.
+    ///
+    /// `ignore` is set if there were more parameters or intermediates than
+    /// the parser can keep.
+    fn csi_dispatch(
+        &mut self,
+        _params: &Params,
+        _intermediates: &[u8],
+        _ignore: bool,
+        _action: char,
+    ) {
+    }
+
+    /// A complete ESC sequence.
+    fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, _byte: u8) {}
+}
+
+/// The VT500-series parser, see [`Perform`].
+///
+/// The parser keeps its state between calls to [`Parser::advance`], so the
+/// input can be fed in arbitrary chunks.
+#[derive(Debug, Default)]
+pub struct Parser {
+    state: State,
+    config: ParserConfig,
+    params: ParamsBuilder,
+    intermediates: [u8; MAX_INTERMEDIATES],
+    intermediate_idx: usize,
+    ignoring: bool,
+    // 序列超出了限制,不再分发
+    exceeded: bool,
+    // 当前序列的长度
+    seq_len: usize,
+    osc_raw: OscRaw,
+    // 未完成的UTF-8字符
+    utf8: [u8; 4],
+    utf8_len: usize,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser with the given config.
+    ///
+    /// With [`ParserConfig::c1_controls`] the bytes `0x80–0x9F` are C1
+    /// controls, and sequences past the limits are not dispatched.
+    /// [`ParserConfig::controls_in_text`] has no effect, C0 controls are
+    /// always passed to [`Perform::execute`].
+    pub fn with_config(config: ParserConfig) -> Self {
+        Parser {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Feeds bytes to the parser.
+    pub fn advance<P: Perform>(&mut self, performer: &mut P, bytes: &[u8]) {
+        for &byte in bytes {
+            self.advance_byte(performer, byte);
+        }
+    }
+
+    fn advance_byte<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        let c1 = self.config.c1_controls && (0x80..=0x9f).contains(&byte);
+        let (mut next, mut action) = if c1 {
+            self.state.advance_c1(byte)
+        } else {
+            self.state.advance(byte)
+        };
+
+        // 超出长度限制的序列被忽略
+        if (c1 || byte == 0x1b) && next.is_introducer() {
+            self.seq_len = 0;
+        }
+        self.seq_len += 1;
+        let too_long = match next {
+            State::Ground | State::SingleShift | State::DcsPassthrough => false,
+            _ if next.is_string() => false,
+            _ => self
+                .config
+                .max_sequence_len
+                .is_some_and(|max| self.seq_len > max),
+        };
+        if too_long {
+            self.exceeded = true;
+            (next, action) = match next {
+                State::CsiEntry | State::CsiParam | State::CsiIntermediate => {
+                    (State::CsiIgnore, Action::None)
+                }
+                State::DcsEntry | State::DcsParam | State::DcsIntermediate => {
+                    (State::DcsIgnore, Action::None)
+                }
+                _ => (next, action),
+            };
+        }
+        // 超出限制的 DCS 忽略到结束符
+        if next == State::DcsPassthrough && self.state != State::DcsPassthrough {
+            self.finish_params();
+            if self.exceeded || self.config.params_exceed(&self.params.params) {
+                next = State::DcsIgnore;
+            }
+        }
+
+        // 状态未变化,且不是`CAN`,`SUB`,`ESC`,C1
+        if next == self.state && !c1 && !matches!(byte, 0x18 | 0x1a | 0x1b) {
+            self.perform_action(performer, action, byte);
+            return;
+        }
+
+        self.perform_action(performer, self.state.exit_action(), byte);
+        self.perform_action(performer, action, byte);
+        self.perform_action(performer, next.entry_action(), byte);
+        self.state = next;
+    }
+
+    fn perform_action<P: Perform>(&mut self, performer: &mut P, action: Action, byte: u8) {
+        // 只有打印字符才能组成UTF-8字符
+        if action != Action::Print && self.utf8_len > 0 {
+            self.utf8_len = 0;
+            performer.print(char::REPLACEMENT_CHARACTER);
+        }
+
+        match action {
+            Action::None | Action::Ignore => {}
+            Action::Print => self.print(performer, byte),
+            Action::Execute => performer.execute(byte),
+            Action::Clear => {
+                self.params.clear();
+                self.intermediate_idx = 0;
+                self.ignoring = false;
+                self.exceeded = false;
+            }
+            Action::Collect => {
+                if self.intermediate_idx == MAX_INTERMEDIATES {
+                    self.ignoring = true;
+                } else {
+                    self.intermediates[self.intermediate_idx] = byte;
+                    self.intermediate_idx += 1;
+                }
+            }
+            Action::Param => {
+                if !self.params.push_byte(byte) {
+                    self.ignoring = true;
+                }
+            }
+            Action::EscDispatch if self.exceeded => {}
+            Action::EscDispatch => {
+                performer.esc_dispatch(self.intermediates(), self.ignoring, byte);
+            }
+            Action::CsiDispatch => {
+                self.finish_params();
+                if self.exceeded || self.config.params_exceed(&self.params.params) {
+                    return;
+                }
+                performer.csi_dispatch(
+                    &self.params.params,
+                    self.intermediates(),
+                    self.ignoring,
+                    byte as char,
+                );
+            }
+            Action::Hook => {
+                self.finish_params();
+                performer.hook(
+                    &self.params.params,
+                    self.intermediates(),
+                    self.ignoring,
+                    byte as char,
+                );
+            }
+            Action::Put => performer.put(byte),
+            Action::Unhook => performer.unhook(),
+            Action::OscStart => {
+                self.osc_raw.clear();
+                self.exceeded = false;
+            }
+            Action::OscPut => {
+                let max = self.config.max_string_len;
+                if max.is_some_and(|max| self.osc_raw.len() >= max) {
+                    self.exceeded = true;
+                } else {
+                    self.osc_raw.push(byte);
+                }
+            }
+            Action::OscEnd if self.exceeded => {}
+            Action::OscEnd => self.osc_dispatch(performer, byte),
+        }
+    }
+
+    #[inline]
+    fn intermediates(&self) -> &[u8] {
+        &self.intermediates[..self.intermediate_idx]
+    }
+
+    fn finish_params(&mut self) {
+        if !self.params.finish() {
+            self.ignoring = true;
+        }
+    }
+
+    fn print<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        // 新的起始字节打断了未完成的字符
+        let is_continuation = byte & 0xc0 == 0x80;
+        if self.utf8_len > 0 && !is_continuation {
+            self.utf8_len = 0;
+            performer.print(char::REPLACEMENT_CHARACTER);
+        }
+
+        if byte.is_ascii() {
+            performer.print(byte as char);
+            return;
+        }
+
+        // 非起始字节,且前面没有未完成的字符
+        if self.utf8_len == 0 && is_continuation {
+            performer.print(char::REPLACEMENT_CHARACTER);
+            return;
+        }
+
+        self.utf8[self.utf8_len] = byte;
+        self.utf8_len += 1;
+
+        let expected = match self.utf8[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => {
+                self.utf8_len = 0;
+                performer.print(char::REPLACEMENT_CHARACTER);
+                return;
+            }
+        };
+
+        if self.utf8_len == expected {
+            let c = core::str::from_utf8(&self.utf8[..expected])
+                .ok()
+                .and_then(|s| s.chars().next())
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.utf8_len = 0;
+            performer.print(c);
+        }
+    }
+
+    fn osc_dispatch<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        let mut params: [&[u8]; MAX_OSC_PARAMS] = [&[]; MAX_OSC_PARAMS];
+        let mut len = 0;
+
+        for param in self.osc_raw.splitn(MAX_OSC_PARAMS, |&b| b == b';') {
+            params[len] = param;
+            len += 1;
+        }
+
+        performer.osc_dispatch(&params[..len], byte == 0x07);
+    }
+}
+
+// 测试用例需要分配内存
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
+    use alloc::{string::String, vec, vec::Vec};
+
+    #[cfg(feature = "std")]
+    use std::{string::String, vec::Vec};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Print(char),
+        Execute(u8),
+        Hook(Vec<Option<u16>>, Vec<u8>, char),
+        Put(u8),
+        Unhook,
+        Osc(Vec<Vec<u8>>, bool),
+        Csi(Vec<Option<u16>>, Vec<u8>, bool, char),
+        Esc(Vec<u8>, u8),
+    }
+
+    #[derive(Default)]
+    struct Events(Vec<Event>);
+
+    impl Perform for Events {
+        fn print(&mut self, c: char) {
+            self.0.push(Event::Print(c));
+        }
+
+        fn execute(&mut self, byte: u8) {
+            self.0.push(Event::Execute(byte));
+        }
+
+        fn hook(&mut self, params: &Params, intermediates: &[u8], _: bool, action: char) {
+            self.0.push(Event::Hook(
+                params.as_slice().to_vec(),
+                intermediates.to_vec(),
+                action,
+            ));
+        }
+
+        fn put(&mut self, byte: u8) {
+            self.0.push(Event::Put(byte));
+        }
+
+        fn unhook(&mut self) {
+            self.0.push(Event::Unhook);
+        }
+
+        fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
+            let params = params.iter().map(|p| p.to_vec()).collect();
+            self.0.push(Event::Osc(params, bell_terminated));
+        }
+
+        fn csi_dispatch(
+            &mut self,
+            params: &Params,
+            intermediates: &[u8],
+            ignore: bool,
+            action: char,
+        ) {
+            self.0.push(Event::Csi(
+                params.as_slice().to_vec(),
+                intermediates.to_vec(),
+                ignore,
+                action,
+            ));
+        }
+
+        fn esc_dispatch(&mut self, intermediates: &[u8], _: bool, byte: u8) {
+            self.0.push(Event::Esc(intermediates.to_vec(), byte));
+        }
+    }
+
+    fn events(bytes: &[u8]) -> Vec<Event> {
+        let mut parser = Parser::new();
+        let mut events = Events::default();
+        parser.advance(&mut events, bytes);
+        events.0
+    }
+
+    #[test]
+    fn csi() {
+        assert_eq!(
+            events(b"\x1b[?1049;2004h\x1b[m\x1b[2 q"),
+            vec![
+                Event::Csi(vec![Some(1049), Some(2004)], vec![b'?'], false, 'h'),
+                Event::Csi(vec![], vec![], false, 'm'),
+                Event::Csi(vec![Some(2)], vec![b' '], false, 'q'),
+            ]
+        );
+    }
+
+    #[test]
+    fn print_and_execute() {
+        assert_eq!(
+            events("a🌍\r\n".as_bytes()),
+            vec![
+                Event::Print('a'),
+                Event::Print('🌍'),
+                Event::Execute(b'\r'),
+                Event::Execute(b'\n'),
+            ]
+        );
+        assert_eq!(
+            events(b"\xff\xe4\xb8a"),
+            vec![
+                Event::Print(char::REPLACEMENT_CHARACTER),
+                Event::Print(char::REPLACEMENT_CHARACTER),
+                Event::Print('a'),
+            ]
+        );
+    }
+
+    #[test]
+    fn esc() {
+        assert_eq!(
+            events(b"\x1b7\x1b(0"),
+            vec![Event::Esc(vec![], b'7'), Event::Esc(vec![b'('], b'0')]
+        );
+    }
+
+    #[test]
+    fn osc() {
+        assert_eq!(
+            events(b"\x1b]0;title\x07\x1b]8;;http://a\x1b\\"),
+            vec![
+                Event::Osc(vec![b"0".to_vec(), b"title".to_vec()], true),
+                Event::Osc(
+                    vec![b"8".to_vec(), b"".to_vec(), b"http://a".to_vec()],
+                    false
+                ),
+                Event::Esc(vec![], b'\\'),
+            ]
+        );
+    }
+
+    #[test]
+    fn dcs() {
+        assert_eq!(
+            events(b"\x1bP1$rq#\x1b\\"),
+            vec![
+                Event::Hook(vec![Some(1)], vec![b'$'], 'r'),
+                Event::Put(b'q'),
+                Event::Put(b'#'),
+                Event::Unhook,
+                Event::Esc(vec![], b'\\'),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_input() {
+        let mut parser = Parser::new();
+        let mut events = Events::default();
+        for chunk in [&b"\x1b[3"[..], b"1m\xf0\x9f", b"\x8c\x8d"] {
+            parser.advance(&mut events, chunk);
+        }
+
+        assert_eq!(
+            events.0,
+            vec![
+                Event::Csi(vec![Some(31)], vec![], false, 'm'),
+                Event::Print('🌍'),
+            ]
+        );
+    }
+
+    #[test]
+    fn control_inside_sequence() {
+        assert_eq!(
+            events(b"\x1b[3\n1m\x1b[31\x18m"),
+            vec![
+                Event::Execute(b'\n'),
+                Event::Csi(vec![Some(31)], vec![], false, 'm'),
+                Event::Execute(0x18),
+                Event::Print('m'),
+            ]
+        );
+    }
+
+    #[test]
+    fn subparams() {
+        struct Groups(Vec<Vec<Option<u16>>>);
+
+        impl Perform for Groups {
+            fn csi_dispatch(&mut self, params: &Params, _: &[u8], _: bool, _: char) {
+                self.0 = params.iter().map(|group| group.to_vec()).collect();
+            }
+        }
+
+        let mut groups = Groups(vec![]);
+        Parser::new().advance(&mut groups, b"\x1b[4:3;38:2::255:0:0;m");
+        assert_eq!(
+            groups.0,
+            vec![
+                vec![Some(4), Some(3)],
+                vec![Some(38), Some(2), None, Some(255), Some(0), Some(0)],
+                vec![None],
+            ]
+        );
+    }
+
+    #[test]
+    fn too_many_params() {
+        let mut text = String::from("\x1b[");
+        for _ in 0..40 {
+            text.push_str("1;");
+        }
+        text.push('m');
+
+        match &events(text.as_bytes())[..] {
+            [Event::Csi(params, _, ignore, 'm')] => {
+                assert_eq!(params.len(), crate::params::MAX_PARAMS);
+                assert!(ignore);
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let config = ParserConfig {
+            max_params: Some(2),
+            max_param_value: Some(1000),
+            max_sequence_len: Some(8),
+            max_string_len: Some(4),
+            ..ParserConfig::default()
+        };
+        let mut parser = Parser::with_config(config);
+        let mut out = Events::default();
+        parser.advance(
+            &mut out,
+            b"\x1b[1;2;3m\x1b[1001m\x1b[123456789m\x1b]0;title\x07",
+        );
+        parser.advance(&mut out, b"\x1bP1;2;3q#\x1b\\\x1b[1;2m\x1b]0;t\x07");
+
+        assert_eq!(
+            out.0,
+            vec![
+                Event::Esc(vec![], b'\\'),
+                Event::Csi(vec![Some(1), Some(2)], vec![], false, 'm'),
+                Event::Osc(vec![b"0".to_vec(), b"t".to_vec()], true),
+            ]
+        );
+    }
+
+    #[test]
+    fn c1_controls() {
+        let config = ParserConfig {
+            c1_controls: true,
+            ..ParserConfig::default()
+        };
+        let mut parser = Parser::with_config(config);
+        let mut out = Events::default();
+        parser.advance(&mut out, b"\x9b1m\x9d0;t\x9c\x85");
+
+        assert_eq!(
+            out.0,
+            vec![
+                Event::Csi(vec![Some(1)], vec![], false, 'm'),
+                Event::Osc(vec![b"0".to_vec(), b"t".to_vec()], false),
+                Event::Execute(0x85),
+            ]
+        );
+
+        // 默认作为UTF-8字符
+        assert_eq!(events("\u{9b}".as_bytes()), vec![Event::Print('\u{9b}')]);
+    }
+}
@@ -0,0 +1,1033 @@
+//! Splits bytes into text runs and sequences, driven by the state machine.
+
+use crate::config::ParserConfig;
+use crate::error::ParseErrorKind;
+use crate::find::find_special;
+use crate::params::{Params, ParamsBuilder};
+use crate::parser::{Input, StringTerminator};
+use crate::state::{Action, State};
+use core::ops::Range;
+
+/// What a [`Token`] covers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum TokenKind {
+    /// Everything between two sequences, including the bytes of sequences
+    /// that are not recognized.
+    Text,
+    /// A dispatched CSI sequence.
+    Csi,
+    /// A dispatched escape sequence, ie `ESC 7`.
+    Esc,
+    /// `SS2` or `SS3` and the character after it.
+    SingleShift,
+    /// A C0 control, or `CAN`/`SUB` aborting a sequence. The controls
+    /// executed inside a sequence are yielded right before it.
+    Control,
+    /// An OSC sequence.
+    Osc(StringTerminator),
+    /// A SOS, PM or APC string.
+    SosPmApc,
+    /// The header of a DCS sequence, up to its final byte.
+    DcsHook,
+    /// A part of the data string of a DCS sequence.
+    DcsPut,
+    /// The end of a DCS sequence, covering its terminator if any. A sequence
+    /// cut off at the end of the input is `truncated`, then the token covers
+    /// the whole sequence.
+    DcsUnhook { truncated: bool },
+    /// A sequence that is aborted or ignored, only yielded by
+    /// [`Scanner::report_errors`].
+    Malformed(ParseErrorKind),
+}
+
+/// A byte range of the input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Token {
+    pub start: usize,
+    pub end: usize,
+    /// Start of the parameters or the string, after the introducer.
+    pub data_start: usize,
+    /// End of the parameters or the string, before the final byte or the
+    /// terminator.
+    pub data_end: usize,
+    pub kind: TokenKind,
+}
+
+impl Token {
+    #[inline]
+    fn text(start: usize, end: usize) -> Self {
+        Token {
+            start,
+            end,
+            data_start: start,
+            data_end: end,
+            kind: TokenKind::Text,
+        }
+    }
+
+    #[inline]
+    fn control(start: usize) -> Self {
+        Token {
+            start,
+            end: start + 1,
+            data_start: start,
+            data_end: start + 1,
+            kind: TokenKind::Control,
+        }
+    }
+}
+
+/// A string sequence that has been interrupted by `ESC`, which is its
+/// terminator if followed by `\`.
+#[derive(Debug, Clone, Copy)]
+struct StringSeq {
+    state: State,
+    start: usize,
+    data_start: usize,
+    // 超出限制的字符串被忽略
+    exceeded: bool,
+}
+
+impl StringSeq {
+    /// The token of the terminated string, if it is recognized.
+    fn token(self, data_end: usize, end: usize, terminator: StringTerminator) -> Option<Token> {
+        if self.exceeded {
+            return None;
+        }
+        let kind = match self.state {
+            State::OscString => TokenKind::Osc(terminator),
+            State::SosPmApcString => TokenKind::SosPmApc,
+            _ => return None,
+        };
+
+        Some(Token {
+            start: self.start,
+            end,
+            data_start: self.data_start,
+            data_end,
+            kind,
+        })
+    }
+}
+
+/// How C1 controls are encoded in the input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum C1 {
+    Disabled,
+    /// A single byte `0x80–0x9F`.
+    Byte,
+    /// The UTF-8 encoding of `U+0080–U+009F`.
+    Utf8,
+}
+
+impl C1 {
+    pub(crate) fn new<T: Input + ?Sized>(config: ParserConfig) -> Self {
+        match (config.c1_controls, T::UTF8) {
+            (false, _) => C1::Disabled,
+            (true, false) => C1::Byte,
+            (true, true) => C1::Utf8,
+        }
+    }
+
+    /// The C1 control at the start of `bytes` and its length.
+    #[inline]
+    pub(crate) fn code(self, bytes: &[u8]) -> Option<(u8, usize)> {
+        match (self, bytes) {
+            (C1::Byte, [code @ 0x80..=0x9f, ..]) => Some((*code, 1)),
+            (C1::Utf8, [0xc2, code @ 0x80..=0x9f, ..]) => Some((*code, 2)),
+            _ => None,
+        }
+    }
+}
+
+/// Yields the tokens of `bytes` on demand.
+pub(crate) struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    state: State,
+    c1: C1,
+    // 当前序列的起始位置
+    seq_start: usize,
+    // 当前序列参数或字符串的起始位置
+    data_start: usize,
+    // 当前序列的参数
+    params: ParamsBuilder,
+    // 被 ESC 打断的字符串
+    string: Option<StringSeq>,
+    // 尚未输出的文本的起始位置
+    text_start: usize,
+    // 文本之后紧跟着的序列
+    pending: Option<Token>,
+    // 当前序列中执行的控制字符,在序列之前输出
+    controls: Range<usize>,
+    // 输入不完整,末尾未完成的序列或字符不作为文本输出
+    partial: bool,
+    // 输入结束时不结束 DCS,后续数据在下一块中
+    keep_dcs: bool,
+    // C0 控制字符单独输出,不作为文本
+    split_controls: bool,
+    // 输出错误的序列,不作为文本
+    report: bool,
+    // 序列的长度和参数限制
+    limits: ParserConfig,
+    // 当前序列超出了限制
+    exceeded: bool,
+}
+
+impl<'a> Scanner<'a> {
+    pub(crate) fn new(bytes: &'a [u8], c1: C1) -> Self {
+        Scanner {
+            bytes,
+            pos: 0,
+            state: State::Ground,
+            c1,
+            seq_start: 0,
+            data_start: 0,
+            params: ParamsBuilder::default(),
+            string: None,
+            text_start: 0,
+            pending: None,
+            controls: 0..0,
+            partial: false,
+            keep_dcs: false,
+            split_controls: false,
+            report: false,
+            limits: ParserConfig::default(),
+            exceeded: false,
+        }
+    }
+
+    /// Ignores sequences going past the limits of `config`.
+    pub(crate) fn limits(self, config: ParserConfig) -> Self {
+        Scanner {
+            limits: config,
+            ..self
+        }
+    }
+
+    /// Yields aborted and ignored sequences as [`TokenKind::Malformed`]
+    /// instead of leaving them in the text.
+    pub(crate) fn report_errors(self) -> Self {
+        Scanner {
+            report: true,
+            ..self
+        }
+    }
+
+    /// Yields C0 controls as [`TokenKind::Control`] instead of leaving them
+    /// in the text.
+    pub(crate) fn split_controls(self, split_controls: bool) -> Self {
+        Scanner {
+            split_controls,
+            ..self
+        }
+    }
+
+    /// Starts scanning in `state`, which is either [`State::Ground`] or
+    /// [`State::DcsPassthrough`].
+    #[cfg(feature = "alloc")]
+    pub(crate) fn with_state(self, state: State) -> Self {
+        Scanner { state, ..self }
+    }
+
+    /// Scans a chunk of a longer input.
+    ///
+    /// An unfinished sequence or UTF-8 character at the end is not yielded,
+    /// see [`Scanner::consumed`].
+    #[cfg(feature = "alloc")]
+    pub(crate) fn partial(self) -> Self {
+        Scanner {
+            partial: true,
+            ..self
+        }
+    }
+
+    /// Leaves a DCS sequence hooked at the end of the input, since its data
+    /// string goes on in the next chunk.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn keep_dcs(self) -> Self {
+        Scanner {
+            keep_dcs: true,
+            ..self
+        }
+    }
+
+    /// Number of bytes covered by the yielded tokens.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn consumed(&self) -> usize {
+        self.text_start
+    }
+
+    /// The parameters of the last yielded CSI sequence.
+    #[inline]
+    pub(crate) fn params(&self) -> &Params {
+        &self.params.params
+    }
+
+    /// Whether the parameters of the last yielded CSI sequence have been
+    /// truncated or saturated.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn params_oversize(&self) -> bool {
+        self.params.is_oversize()
+    }
+
+    /// The state after the last scanned byte.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn state(&self) -> State {
+        self.state
+    }
+
+    /// The state to resume from at [`Scanner::consumed`], which is inside the
+    /// data string if a DCS sequence has been hooked but not unhooked.
+    pub(crate) fn resume_state(&self) -> State {
+        match self.string {
+            Some(string) if string.state == State::DcsPassthrough => State::DcsPassthrough,
+            _ if self.state == State::DcsPassthrough => State::DcsPassthrough,
+            _ => State::Ground,
+        }
+    }
+
+    /// Ends a DCS sequence whose terminator is `start..end`, yielding the
+    /// data before it first, if any.
+    fn unhook(&mut self, data_start: usize, start: usize, end: usize) -> Token {
+        self.text_start = end;
+        let unhook = Token {
+            start,
+            end,
+            data_start: start,
+            data_end: start,
+            kind: TokenKind::DcsUnhook { truncated: false },
+        };
+        if data_start < start {
+            self.pending = Some(unhook);
+            return Token {
+                start: data_start,
+                end: start,
+                data_start,
+                data_end: start,
+                kind: TokenKind::DcsPut,
+            };
+        }
+
+        unhook
+    }
+
+    /// Ends a single shift, which applies to `byte` if it is a graphic
+    /// character.
+    ///
+    /// A single shift without a character is an escape sequence if it is
+    /// `ESC N` or `ESC O`, otherwise it stays in the text like other C1
+    /// controls.
+    fn single_shift(&mut self, byte: Option<u8>) -> Option<Token> {
+        self.state = State::Ground;
+
+        if let Some(0x20..=0x7e) = byte {
+            self.pos += 1;
+            let token = Token {
+                start: self.seq_start,
+                end: self.pos,
+                data_start: self.pos - 1,
+                data_end: self.pos,
+                kind: TokenKind::SingleShift,
+            };
+            return Some(self.emit(token));
+        }
+
+        if self.bytes[self.seq_start] != 0x1b {
+            return None;
+        }
+        let token = Token {
+            start: self.seq_start,
+            end: self.pos,
+            data_start: self.seq_start + 1,
+            data_end: self.pos - 1,
+            kind: TokenKind::Esc,
+        };
+        Some(self.emit(token))
+    }
+
+    /// Yields the text before `token` first, if any, then the controls
+    /// executed inside it.
+    fn emit(&mut self, token: Token) -> Token {
+        let text_start = core::mem::replace(&mut self.text_start, token.end);
+        if text_start < token.start {
+            self.pending = Some(token);
+            return Token::text(text_start, token.start);
+        }
+        if let Some(control) = self.control(token) {
+            self.pending = Some(token);
+            return control;
+        }
+
+        token
+    }
+
+    /// The next C0 control executed inside `token` that has not been yielded.
+    fn control(&mut self, token: Token) -> Option<Token> {
+        // 不属于该序列的控制字符已作为文本输出
+        if self.controls.start < token.start || self.controls.end > token.end {
+            self.controls.start = self.controls.end;
+            return None;
+        }
+
+        let bytes = &self.bytes[self.controls.clone()];
+        match bytes.iter().position(|&b| b < 0x20) {
+            Some(index) => {
+                let start = self.controls.start + index;
+                self.controls.start = start + 1;
+                Some(Token::control(start))
+            }
+            None => {
+                self.controls.start = self.controls.end;
+                None
+            }
+        }
+    }
+
+    /// Yields `start..end` as a malformed sequence if errors are reported.
+    fn malformed(&mut self, start: usize, end: usize, kind: ParseErrorKind) -> Option<Token> {
+        if !self.report || start == end {
+            return None;
+        }
+
+        let token = Token {
+            start,
+            end,
+            data_start: start,
+            data_end: end,
+            kind: TokenKind::Malformed(kind),
+        };
+        Some(self.emit(token))
+    }
+
+    /// Whether the sequence goes past the length limits once in `state` with
+    /// the input up to `end`.
+    fn exceeds(&self, state: State, end: usize) -> bool {
+        match state {
+            State::Ground | State::SingleShift | State::DcsPassthrough => false,
+            _ if state.is_string() => {
+                let max = self.limits.max_string_len;
+                max.is_some_and(|max| end - self.data_start > max)
+            }
+            _ => {
+                let max = self.limits.max_sequence_len;
+                max.is_some_and(|max| end - self.seq_start > max)
+            }
+        }
+    }
+
+    /// Whether the byte, or the C1 control starting with it, aborts the
+    /// current sequence.
+    fn aborts(&self, byte: u8, c1: Option<(u8, usize)>) -> bool {
+        match (self.state, c1) {
+            (State::Ground | State::SingleShift | State::DcsPassthrough, _) => false,
+            // ST 结束字符串
+            (state, Some((0x9c, _))) => !state.is_string(),
+            (_, Some(_)) => true,
+            (state, None) => matches!(byte, 0x18 | 0x1a) || (byte == 0x1b && !state.is_string()),
+        }
+    }
+
+    /// End of the text at the end of the input.
+    fn text_end(&self) -> usize {
+        if !self.partial {
+            return self.bytes.len();
+        }
+
+        if self.resume_state() == State::DcsPassthrough {
+            return self.text_start;
+        }
+        if self.state != State::Ground {
+            return self.string.map_or(self.seq_start, |string| string.start);
+        }
+
+        self.bytes.len() - incomplete_utf8(self.bytes)
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.take() {
+            if let Some(control) = self.control(token) {
+                self.pending = Some(token);
+                return Some(control);
+            }
+            return Some(token);
+        }
+
+        while self.pos < self.bytes.len() {
+            // ground 状态下的文本整段跳过
+            if self.state == State::Ground {
+                let bytes = &self.bytes[self.pos..];
+                self.pos += find_special(bytes, self.split_controls, self.c1);
+                if self.pos == self.bytes.len() {
+                    break;
+                }
+            }
+
+            let byte = self.bytes[self.pos];
+
+            // `ESC \` 结束字符串
+            if let Some(string) = self.string.take() {
+                if byte == b'\\' {
+                    self.state = State::Ground;
+                    self.pos += 1;
+                    if string.state == State::DcsPassthrough {
+                        return Some(self.unhook(string.data_start, self.pos - 2, self.pos));
+                    }
+                    if let Some(token) = string.token(self.pos - 2, self.pos, StringTerminator::St)
+                    {
+                        return Some(self.emit(token));
+                    }
+                    if let Some(token) = self.malformed(
+                        string.start,
+                        self.pos,
+                        error_kind(string.state, string.exceeded),
+                    ) {
+                        return Some(token);
+                    }
+                    continue;
+                }
+                // 被其它序列打断的 DCS
+                if string.state == State::DcsPassthrough {
+                    return Some(self.unhook(string.data_start, self.seq_start, self.seq_start));
+                }
+                let kind = error_kind(string.state, string.exceeded);
+                if let Some(token) = self.malformed(string.start, self.seq_start, kind) {
+                    return Some(token);
+                }
+            }
+
+            // SS2 或 SS3 之后的字符
+            if self.state == State::SingleShift {
+                match self.single_shift(Some(byte)) {
+                    Some(token) => return Some(token),
+                    None => continue,
+                }
+            }
+
+            let c1 = self.c1.code(&self.bytes[self.pos..]);
+
+            // 被打断的序列,在 ground 状态重新处理该字节
+            if self.report && self.aborts(byte, c1) {
+                let kind = error_kind(self.state, self.exceeded);
+                self.state = State::Ground;
+                if let Some(token) = self.malformed(self.seq_start, self.pos, kind) {
+                    return Some(token);
+                }
+                continue;
+            }
+
+            let (mut next, mut action) = match c1 {
+                Some((code, _)) => self.state.advance_c1(code),
+                None => self.state.advance(byte),
+            };
+            let len = c1.map_or(1, |(_, len)| len);
+            // C1 开始新序列时会覆盖,先保存 DCS 数据的起始位置
+            let put_start = self.data_start;
+
+            // ESC 或 C1 开始一个新的序列
+            let restart = byte == 0x1b || c1.is_some();
+            if restart && next.is_introducer() {
+                if byte == 0x1b && self.state.is_string() {
+                    self.string = Some(StringSeq {
+                        state: self.state,
+                        start: self.seq_start,
+                        data_start: self.data_start,
+                        exceeded: self.exceeded,
+                    });
+                }
+                self.seq_start = self.pos;
+                self.controls = self.pos..self.pos;
+                self.exceeded = false;
+            }
+            if (restart || next != self.state) && next.has_data() {
+                self.data_start = self.pos + len;
+                self.params.clear();
+            }
+            // 超出长度限制的序列被忽略
+            if self.exceeds(next, self.pos + len) {
+                self.exceeded = true;
+                (next, action) = match next {
+                    State::CsiEntry | State::CsiParam | State::CsiIntermediate => {
+                        (State::CsiIgnore, Action::None)
+                    }
+                    State::DcsEntry | State::DcsParam | State::DcsIntermediate => {
+                        (State::DcsIgnore, Action::None)
+                    }
+                    _ => (next, action),
+                };
+            }
+            if action == Action::Param {
+                self.params.push_byte(byte);
+            }
+
+            // BEL 或 8-bit ST 结束字符串
+            let terminated = match (self.state.is_string(), next, c1) {
+                (true, State::Ground, Some((0x9c, _))) => Some(StringTerminator::St),
+                (true, State::Ground, None) if byte == 0x07 => Some(StringTerminator::Bel),
+                _ => None,
+            };
+            let string = StringSeq {
+                state: self.state,
+                start: self.seq_start,
+                data_start: self.data_start,
+                exceeded: self.exceeded,
+            };
+
+            let prev = core::mem::replace(&mut self.state, next);
+            self.pos += len;
+
+            if prev != State::DcsPassthrough && next == State::DcsPassthrough {
+                // 超出限制的 DCS 忽略到结束符
+                self.params.finish();
+                if self.exceeded || self.limits.params_exceed(self.params()) {
+                    self.exceeded = true;
+                    self.state = State::DcsIgnore;
+                    continue;
+                }
+                let token = Token {
+                    start: self.seq_start,
+                    end: self.pos,
+                    data_start: self.data_start,
+                    data_end: self.pos - 1,
+                    kind: TokenKind::DcsHook,
+                };
+                self.data_start = self.pos;
+                return Some(self.emit(token));
+            }
+            // ESC 之外的字节结束 DCS,只有 ST 属于该序列
+            if prev == State::DcsPassthrough && next != State::DcsPassthrough && byte != 0x1b {
+                let start = self.pos - len;
+                if terminated.is_some() {
+                    return Some(self.unhook(put_start, start, self.pos));
+                }
+                // 在 ground 状态重新处理该字节
+                self.state = State::Ground;
+                self.pos = start;
+                return Some(self.unhook(put_start, start, start));
+            }
+
+            if let Some(terminator) = terminated {
+                if let Some(token) = string.token(self.pos - len, self.pos, terminator) {
+                    return Some(self.emit(token));
+                }
+                if let Some(token) = self.malformed(
+                    string.start,
+                    self.pos,
+                    error_kind(string.state, string.exceeded),
+                ) {
+                    return Some(token);
+                }
+            }
+
+            // C0 控制字符,`CAN`/`SUB` 也会结束序列
+            if self.split_controls && action == Action::Execute && c1.is_none() {
+                // 序列中的控制字符在序列结束时输出
+                if prev != State::Ground && next != State::Ground {
+                    if self.controls.is_empty() {
+                        self.controls.start = self.pos - 1;
+                    }
+                    self.controls.end = self.pos;
+                    continue;
+                }
+                let token = Token::control(self.pos - 1);
+                return Some(self.emit(token));
+            }
+
+            // 被忽略的 CSI 在结束字节处结束
+            if prev == State::CsiIgnore && next == State::Ground {
+                let kind = error_kind(prev, self.exceeded);
+                if let Some(token) = self.malformed(self.seq_start, self.pos, kind) {
+                    return Some(token);
+                }
+            }
+
+            if action == Action::CsiDispatch {
+                self.params.finish();
+                if self.exceeded || self.limits.params_exceed(self.params()) {
+                    self.exceeded = true;
+                    let kind = ParseErrorKind::LimitExceeded;
+                    if let Some(token) = self.malformed(self.seq_start, self.pos, kind) {
+                        return Some(token);
+                    }
+                    continue;
+                }
+                let token = Token {
+                    start: self.seq_start,
+                    end: self.pos,
+                    data_start: self.data_start,
+                    data_end: self.pos - 1,
+                    kind: TokenKind::Csi,
+                };
+                return Some(self.emit(token));
+            }
+
+            // SS2 和 SS3 等待下一个字符
+            if action == Action::EscDispatch && next != State::SingleShift {
+                if self.exceeded {
+                    let kind = ParseErrorKind::LimitExceeded;
+                    if let Some(token) = self.malformed(self.seq_start, self.pos, kind) {
+                        return Some(token);
+                    }
+                    continue;
+                }
+                let token = Token {
+                    start: self.seq_start,
+                    end: self.pos,
+                    data_start: self.seq_start + 1,
+                    data_end: self.pos - 1,
+                    kind: TokenKind::Esc,
+                };
+                return Some(self.emit(token));
+            }
+        }
+
+        if self.state == State::SingleShift && !self.partial {
+            if let Some(token) = self.single_shift(None) {
+                return Some(token);
+            }
+        }
+
+        // 尚未结束的 DCS 数据
+        if self.resume_state() == State::DcsPassthrough {
+            // 输入结束时 DCS 也结束,末尾的 ESC 是未完成的序列
+            if !self.partial && !self.keep_dcs {
+                let (start, end) = match self.string.take() {
+                    Some(string) => (string.start, self.seq_start),
+                    None => {
+                        self.state = State::Ground;
+                        (self.seq_start, self.bytes.len())
+                    }
+                };
+                let mut token = self.unhook(self.text_start, end, end);
+                // 截断的 DCS 覆盖整个序列,数据在它之前输出
+                let unhook = match &mut self.pending {
+                    Some(unhook) => unhook,
+                    None => &mut token,
+                };
+                unhook.start = start;
+                unhook.kind = TokenKind::DcsUnhook { truncated: true };
+                return Some(token);
+            }
+            let end = match self.string {
+                Some(_) => self.seq_start,
+                None if self.partial => {
+                    self.bytes.len() - incomplete_utf8(&self.bytes[self.text_start..])
+                }
+                None => self.bytes.len(),
+            };
+            if self.text_start < end {
+                let start = core::mem::replace(&mut self.text_start, end);
+                return Some(Token {
+                    start,
+                    end,
+                    data_start: start,
+                    data_end: end,
+                    kind: TokenKind::DcsPut,
+                });
+            }
+        }
+
+        // 未结束的序列
+        if self.report && !self.partial && self.resume_state() == State::Ground {
+            let (start, kind) = match self.string.take() {
+                Some(string) => (string.start, error_kind(string.state, string.exceeded)),
+                None => (self.seq_start, error_kind(self.state, self.exceeded)),
+            };
+            if core::mem::replace(&mut self.state, State::Ground) != State::Ground {
+                if let Some(token) = self.malformed(start, self.bytes.len(), kind) {
+                    return Some(token);
+                }
+            }
+        }
+
+        // 剩余的文本
+        let end = self.text_end();
+        if self.text_start < end {
+            let start = core::mem::replace(&mut self.text_start, end);
+            return Some(Token::text(start, end));
+        }
+
+        None
+    }
+}
+
+/// The error of a sequence that ends in `state` without being dispatched,
+/// `exceeded` is set if it went past the limits.
+fn error_kind(state: State, exceeded: bool) -> ParseErrorKind {
+    match state {
+        _ if exceeded => ParseErrorKind::LimitExceeded,
+        State::CsiIgnore | State::DcsIgnore => ParseErrorKind::InvalidFinalByte,
+        _ => ParseErrorKind::TruncatedSequence,
+    }
+}
+
+/// Length of an unfinished UTF-8 character at the end of `bytes`.
+pub(crate) fn incomplete_utf8(bytes: &[u8]) -> usize {
+    // 最多回溯3个字节寻找起始字节
+    for len in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - len];
+        if byte & 0xc0 == 0x80 {
+            continue;
+        }
+
+        let width = match byte {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return 0,
+        };
+        return if width > len { len } else { 0 };
+    }
+
+    0
+}
+
+// 测试用例需要分配内存
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
+    use alloc::{vec, vec::Vec};
+
+    #[cfg(feature = "std")]
+    use std::vec::Vec;
+
+    use super::*;
+
+    fn tokens(scanner: Scanner) -> Vec<(usize, usize, TokenKind)> {
+        scanner.map(|t| (t.start, t.end, t.kind)).collect()
+    }
+
+    #[test]
+    fn text_and_csi() {
+        assert_eq!(
+            tokens(Scanner::new(b"a\x1b[1mb\x1b7c\x1b[m", C1::Disabled)),
+            vec![
+                (0, 1, TokenKind::Text),
+                (1, 5, TokenKind::Csi),
+                (5, 6, TokenKind::Text),
+                (6, 8, TokenKind::Esc),
+                (8, 9, TokenKind::Text),
+                (9, 12, TokenKind::Csi),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_sequence() {
+        assert_eq!(
+            tokens(Scanner::new(b"a\x1b[31", C1::Disabled)),
+            vec![(0, 5, TokenKind::Text)]
+        );
+
+        let mut scanner = Scanner::new(b"a\x1b[31", C1::Disabled).partial();
+        assert_eq!(scanner.next(), Some(Token::text(0, 1)));
+        assert_eq!(scanner.next(), None);
+        assert_eq!(scanner.consumed(), 1);
+        assert_eq!(scanner.state(), State::CsiParam);
+    }
+
+    #[test]
+    fn trailing_utf8() {
+        let mut scanner = Scanner::new(b"a\xf0\x9f\x8c", C1::Disabled).partial();
+        assert_eq!(
+            tokens(Scanner::new(b"a\xf0\x9f\x8c", C1::Disabled).partial()),
+            vec![(0, 1, TokenKind::Text)]
+        );
+        while scanner.next().is_some() {}
+        assert_eq!(scanner.consumed(), 1);
+
+        assert_eq!(incomplete_utf8("a🌍".as_bytes()), 0);
+        assert_eq!(incomplete_utf8(b"\xe4"), 1);
+        assert_eq!(incomplete_utf8(b"\xff"), 0);
+    }
+
+    #[test]
+    fn osc() {
+        let bytes = b"a\x1b]0;t\x07\x1b]8;;u\x1b\\\x1b]2;x\x1b[1m";
+        let tokens: Vec<_> = Scanner::new(bytes, C1::Disabled).collect();
+
+        assert_eq!(tokens[1].kind, TokenKind::Osc(StringTerminator::Bel));
+        assert_eq!(&bytes[tokens[1].data_start..tokens[1].data_end], b"0;t");
+        assert_eq!(tokens[2].kind, TokenKind::Osc(StringTerminator::St));
+        assert_eq!(&bytes[tokens[2].data_start..tokens[2].data_end], b"8;;u");
+        assert_eq!((tokens[2].start, tokens[2].end), (7, 15));
+        // 被 ESC 打断的 OSC 作为文本
+        assert_eq!(
+            (tokens[3].start, tokens[3].end, tokens[3].kind),
+            (15, 20, TokenKind::Text)
+        );
+        assert_eq!(tokens[4].kind, TokenKind::Csi);
+    }
+
+    #[test]
+    fn osc_c1() {
+        let tokens: Vec<_> = Scanner::new("\u{9d}0;t\u{9c}".as_bytes(), C1::Utf8).collect();
+        assert_eq!(
+            tokens,
+            vec![Token {
+                start: 0,
+                end: 7,
+                data_start: 2,
+                data_end: 5,
+                kind: TokenKind::Osc(StringTerminator::St),
+            }]
+        );
+    }
+
+    #[test]
+    fn partial_osc_terminator() {
+        let mut scanner = Scanner::new(b"a\x1b]0;t\x1b", C1::Disabled).partial();
+        assert_eq!(scanner.next(), Some(Token::text(0, 1)));
+        assert_eq!(scanner.next(), None);
+        assert_eq!(scanner.consumed(), 1);
+        assert_eq!(scanner.state(), State::Escape);
+    }
+
+    #[test]
+    fn dcs() {
+        assert_eq!(
+            tokens(Scanner::new(b"\x90qab\x9cc\x90q\x9b1m", C1::Byte)),
+            vec![
+                (0, 2, TokenKind::DcsHook),
+                (2, 4, TokenKind::DcsPut),
+                (4, 5, TokenKind::DcsUnhook { truncated: false }),
+                (5, 6, TokenKind::Text),
+                (6, 8, TokenKind::DcsHook),
+                // 被 8-bit CSI 打断
+                (8, 8, TokenKind::DcsUnhook { truncated: false }),
+                (8, 11, TokenKind::Csi),
+            ]
+        );
+
+        let mut scanner = Scanner::new(b"\x1bPqab\x1b", C1::Disabled).partial();
+        assert_eq!(
+            scanner
+                .by_ref()
+                .map(|t| (t.start, t.end, t.kind))
+                .collect::<Vec<_>>(),
+            vec![(0, 3, TokenKind::DcsHook), (3, 5, TokenKind::DcsPut)]
+        );
+        assert_eq!(scanner.consumed(), 5);
+        assert_eq!(scanner.resume_state(), State::DcsPassthrough);
+    }
+
+    #[test]
+    fn sos_pm_apc() {
+        let bytes = b"\x1b_Gf=24;AAAA\x1b\\\x1b^pm\x07\x1b\\";
+        let tokens: Vec<_> = Scanner::new(bytes, C1::Disabled).collect();
+
+        assert_eq!(tokens[0].kind, TokenKind::SosPmApc);
+        assert_eq!(
+            &bytes[tokens[0].data_start..tokens[0].data_end],
+            b"Gf=24;AAAA"
+        );
+        // BEL 不结束 PM
+        assert_eq!(tokens[1].kind, TokenKind::SosPmApc);
+        assert_eq!(&bytes[tokens[1].data_start..tokens[1].data_end], b"pm\x07");
+    }
+
+    #[test]
+    fn single_shift() {
+        assert_eq!(
+            tokens(Scanner::new(b"\x1bOPa\x8eb\x1bO\n\x1bO", C1::Byte)),
+            vec![
+                (0, 3, TokenKind::SingleShift),
+                (3, 4, TokenKind::Text),
+                (4, 6, TokenKind::SingleShift),
+                // 没有字符的 SS3
+                (6, 8, TokenKind::Esc),
+                (8, 9, TokenKind::Text),
+                (9, 11, TokenKind::Esc),
+            ]
+        );
+
+        let mut scanner = Scanner::new(b"a\x1bO", C1::Disabled).partial();
+        assert_eq!(scanner.next(), Some(Token::text(0, 1)));
+        assert_eq!(scanner.next(), None);
+        assert_eq!(scanner.state(), State::SingleShift);
+    }
+
+    #[test]
+    fn controls() {
+        let bytes = b"a\r\nb\x1b[1\n2m\x1b[3\x18";
+        assert_eq!(
+            tokens(Scanner::new(bytes, C1::Disabled).split_controls(true)),
+            vec![
+                (0, 1, TokenKind::Text),
+                (1, 2, TokenKind::Control),
+                (2, 3, TokenKind::Control),
+                (3, 4, TokenKind::Text),
+                // 序列中执行的控制字符在序列之前输出
+                (7, 8, TokenKind::Control),
+                (4, 10, TokenKind::Csi),
+                (10, 13, TokenKind::Text),
+                (13, 14, TokenKind::Control),
+            ]
+        );
+        let truncated = TokenKind::Malformed(ParseErrorKind::TruncatedSequence);
+        assert_eq!(
+            tokens(
+                Scanner::new(b"\x1b\n]0;\x01\x07\x1b[1\r\x1b\t7\x1b[\n", C1::Disabled)
+                    .split_controls(true)
+                    .report_errors()
+            ),
+            vec![
+                // OSC 中的控制字符被忽略
+                (1, 2, TokenKind::Control),
+                (0, 7, TokenKind::Osc(StringTerminator::Bel)),
+                (10, 11, TokenKind::Control),
+                (7, 11, truncated),
+                (12, 13, TokenKind::Control),
+                (11, 14, TokenKind::Esc),
+                (16, 17, TokenKind::Control),
+                (14, 17, truncated),
+            ]
+        );
+        assert_eq!(
+            tokens(Scanner::new(bytes, C1::Disabled)),
+            vec![
+                (0, 4, TokenKind::Text),
+                (4, 10, TokenKind::Csi),
+                (10, 14, TokenKind::Text),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_errors() {
+        let truncated = TokenKind::Malformed(ParseErrorKind::TruncatedSequence);
+        let invalid = TokenKind::Malformed(ParseErrorKind::InvalidFinalByte);
+        let bytes = b"a\x1b[1\x18b\x1b[1<2mc\x1b]0";
+        assert_eq!(
+            tokens(
+                Scanner::new(bytes, C1::Disabled)
+                    .split_controls(true)
+                    .report_errors()
+            ),
+            vec![
+                (0, 1, TokenKind::Text),
+                (1, 4, truncated),
+                (4, 5, TokenKind::Control),
+                (5, 6, TokenKind::Text),
+                (6, 12, invalid),
+                (12, 13, TokenKind::Text),
+                (13, 16, truncated),
+            ]
+        );
+        assert_eq!(
+            tokens(Scanner::new(bytes, C1::Disabled).split_controls(true)),
+            vec![
+                (0, 4, TokenKind::Text),
+                (4, 5, TokenKind::Control),
+                (5, 16, TokenKind::Text)
+            ]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! The DEC VT500-series parser state machine.
+//!
+//! See [A parser for DEC's ANSI-compatible video terminals](https://vt100.net/emu/dec_ansi_parser).
+
+/// Parser states.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub(crate) enum State {
+    #[default]
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    CsiEntry,
+    CsiParam,
+    CsiIntermediate,
+    CsiIgnore,
+    DcsEntry,
+    DcsParam,
+    DcsIntermediate,
+    DcsPassthrough,
+    DcsIgnore,
+    OscString,
+    SosPmApcString,
+    /// After `SS2` or `SS3`, which apply to the next character. Otherwise the
+    /// same as `Ground`.
+    SingleShift,
+}
+
+/// Actions performed on a state transition.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Action {
+    None,
+    Ignore,
+    Print,
+    Execute,
+    Clear,
+    Collect,
+    Param,
+    EscDispatch,
+    CsiDispatch,
+    Hook,
+    Put,
+    Unhook,
+    OscStart,
+    OscPut,
+    OscEnd,
+}
+
+/// C0 controls that are executed in most states (`0x00–0x17`, `0x19`, `0x1C–0x1F`).
+#[inline(always)]
+pub(crate) fn executable_byte(byte: u8) -> bool {
+    matches!(byte, 0x00..=0x17 | 0x19 | 0x1c..=0x1f)
+}
+
+impl State {
+    /// Feeds one byte, returning the next state and the action of the transition.
+    ///
+    /// The `exit` action of the current state and the `entry` action of the
+    /// next state are not included, see [`State::exit_action`] and
+    /// [`State::entry_action`].
+    pub(crate) fn advance(self, byte: u8) -> (State, Action) {
+        use Action::*;
+        use State::*;
+
+        // transitions from anywhere
+        match byte {
+            0x18 | 0x1a => return (Ground, Execute),
+            0x1b => return (Escape, None),
+            _ => {}
+        }
+
+        match self {
+            Ground | SingleShift => match byte {
+                b if executable_byte(b) => (Ground, Execute),
+                _ => (Ground, Print),
+            },
+
+            Escape => match byte {
+                b if executable_byte(b) => (Escape, Execute),
+                0x20..=0x2f => (EscapeIntermediate, Collect),
+                b'P' => (DcsEntry, None),
+                b'[' => (CsiEntry, None),
+                b']' => (OscString, None),
+                b'X' | b'^' | b'_' => (SosPmApcString, None),
+                // SS2, SS3
+                b'N' | b'O' => (SingleShift, EscDispatch),
+                0x30..=0x7e => (Ground, EscDispatch),
+                _ => (Escape, Ignore),
+            },
+
+            EscapeIntermediate => match byte {
+                b if executable_byte(b) => (EscapeIntermediate, Execute),
+                0x20..=0x2f => (EscapeIntermediate, Collect),
+                0x30..=0x7e => (Ground, EscDispatch),
+                _ => (EscapeIntermediate, Ignore),
+            },
+
+            CsiEntry => match byte {
+                b if executable_byte(b) => (CsiEntry, Execute),
+                0x20..=0x2f => (CsiIntermediate, Collect),
+                0x30..=0x3b => (CsiParam, Param),
+                0x3c..=0x3f => (CsiParam, Collect),
+                0x40..=0x7e => (Ground, CsiDispatch),
+                0x7f => (CsiEntry, Ignore),
+                _ => (CsiIgnore, None),
+            },
+
+            CsiParam => match byte {
+                b if executable_byte(b) => (CsiParam, Execute),
+                0x20..=0x2f => (CsiIntermediate, Collect),
+                0x30..=0x3b => (CsiParam, Param),
+                0x40..=0x7e => (Ground, CsiDispatch),
+                0x7f => (CsiParam, Ignore),
+                _ => (CsiIgnore, None),
+            },
+
+            CsiIntermediate => match byte {
+                b if executable_byte(b) => (CsiIntermediate, Execute),
+                0x20..=0x2f => (CsiIntermediate, Collect),
+                0x40..=0x7e => (Ground, CsiDispatch),
+                0x7f => (CsiIntermediate, Ignore),
+                _ => (CsiIgnore, None),
+            },
+
+            CsiIgnore => match byte {
+                b if executable_byte(b) => (CsiIgnore, Execute),
+                0x40..=0x7e => (Ground, None),
+                _ => (CsiIgnore, Ignore),
+            },
+
+            DcsEntry => match byte {
+                0x20..=0x2f => (DcsIntermediate, Collect),
+                0x30..=0x3b => (DcsParam, Param),
+                0x3c..=0x3f => (DcsParam, Collect),
+                0x40..=0x7e => (DcsPassthrough, None),
+                b if b < 0x20 || b == 0x7f => (DcsEntry, Ignore),
+                _ => (DcsIgnore, None),
+            },
+
+            DcsParam => match byte {
+                0x20..=0x2f => (DcsIntermediate, Collect),
+                0x30..=0x3b => (DcsParam, Param),
+                0x40..=0x7e => (DcsPassthrough, None),
+                b if b < 0x20 || b == 0x7f => (DcsParam, Ignore),
+                _ => (DcsIgnore, None),
+            },
+
+            DcsIntermediate => match byte {
+                0x20..=0x2f => (DcsIntermediate, Collect),
+                0x40..=0x7e => (DcsPassthrough, None),
+                b if b < 0x20 || b == 0x7f => (DcsIntermediate, Ignore),
+                _ => (DcsIgnore, None),
+            },
+
+            DcsPassthrough => match byte {
+                0x7f => (DcsPassthrough, Ignore),
+                _ => (DcsPassthrough, Put),
+            },
+
+            DcsIgnore => (DcsIgnore, Ignore),
+
+            OscString => match byte {
+                // xterm accepts BEL as the string terminator
+                0x07 => (Ground, None),
+                b if b < 0x20 => (OscString, Ignore),
+                _ => (OscString, OscPut),
+            },
+
+            SosPmApcString => (SosPmApcString, Ignore),
+        }
+    }
+
+    /// Feeds a C1 control (`0x80–0x9F`), which has the same transition from
+    /// every state.
+    pub(crate) fn advance_c1(self, code: u8) -> (State, Action) {
+        match code {
+            0x90 => (State::DcsEntry, Action::None),
+            0x98 | 0x9e | 0x9f => (State::SosPmApcString, Action::None),
+            0x9b => (State::CsiEntry, Action::None),
+            // ST
+            0x9c => (State::Ground, Action::None),
+            0x9d => (State::OscString, Action::None),
+            // SS2, SS3
+            0x8e | 0x8f => (State::SingleShift, Action::Execute),
+            _ => (State::Ground, Action::Execute),
+        }
+    }
+
+    /// Whether this state starts a new sequence when entered from anywhere.
+    #[inline]
+    pub(crate) fn is_introducer(self) -> bool {
+        matches!(
+            self,
+            State::Escape
+                | State::CsiEntry
+                | State::DcsEntry
+                | State::OscString
+                | State::SosPmApcString
+                | State::SingleShift
+        )
+    }
+
+    /// Whether this state collects parameters or a string.
+    #[inline]
+    pub(crate) fn has_data(self) -> bool {
+        matches!(
+            self,
+            State::CsiEntry | State::DcsEntry | State::OscString | State::SosPmApcString
+        )
+    }
+
+    /// Whether this state is inside a control string, which ends with `ST`.
+    #[inline]
+    pub(crate) fn is_string(self) -> bool {
+        matches!(
+            self,
+            State::OscString | State::DcsPassthrough | State::DcsIgnore | State::SosPmApcString
+        )
+    }
+
+    /// The action performed when entering this state.
+    #[inline]
+    pub(crate) fn entry_action(self) -> Action {
+        match self {
+            State::Escape | State::CsiEntry | State::DcsEntry => Action::Clear,
+            State::DcsPassthrough => Action::Hook,
+            State::OscString => Action::OscStart,
+            _ => Action::None,
+        }
+    }
+
+    /// The action performed when leaving this state.
+    #[inline]
+    pub(crate) fn exit_action(self) -> Action {
+        match self {
+            State::DcsPassthrough => Action::Unhook,
+            State::OscString => Action::OscEnd,
+            _ => Action::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(bytes: &[u8]) -> State {
+        bytes
+            .iter()
+            .fold(State::Ground, |state, &byte| state.advance(byte).0)
+    }
+
+    #[test]
+    fn csi_dispatch() {
+        let mut state = State::Ground;
+        let mut actions = [Action::None; 5];
+        for (i, &byte) in b"\x1b[1m".iter().enumerate() {
+            let (next, action) = state.advance(byte);
+            state = next;
+            actions[i] = action;
+        }
+
+        assert_eq!(state, State::Ground);
+        assert_eq!(actions[3], Action::CsiDispatch);
+    }
+
+    #[test]
+    fn cancel_aborts_sequence() {
+        assert_eq!(run(b"\x1b[31"), State::CsiParam);
+        assert_eq!(run(b"\x1b[31\x18"), State::Ground);
+        assert_eq!(run(b"\x1b]0;title\x1a"), State::Ground);
+    }
+
+    #[test]
+    fn escape_restarts_sequence() {
+        assert_eq!(run(b"\x1b[31\x1b"), State::Escape);
+        assert_eq!(run(b"\x1bP1\x1b["), State::CsiEntry);
+    }
+
+    #[test]
+    fn control_inside_sequence() {
+        assert_eq!(
+            State::CsiParam.advance(b'\n'),
+            (State::CsiParam, Action::Execute)
+        );
+        assert_eq!(
+            State::CsiIntermediate.advance(0x08),
+            (State::CsiIntermediate, Action::Execute)
+        );
+    }
+
+    #[test]
+    fn invalid_csi_is_ignored() {
+        assert_eq!(run(b"\x1b[1<"), State::CsiIgnore);
+        assert_eq!(run(b"\x1b[1<m"), State::Ground);
+        assert_eq!(run(b"\x1b[ 1"), State::CsiIgnore);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(run(b"\x1b]0;title\x07"), State::Ground);
+        assert_eq!(run(b"\x1bPq#0"), State::DcsPassthrough);
+        assert_eq!(run(b"\x1b_Gf=24"), State::SosPmApcString);
+        assert_eq!(run(b"\x1b_Gf=24\x1b\\"), State::Ground);
+    }
+
+    #[test]
+    fn single_shift() {
+        assert_eq!(
+            State::Escape.advance(b'O'),
+            (State::SingleShift, Action::EscDispatch)
+        );
+        assert_eq!(run(b"\x1bOP"), State::Ground);
+        assert_eq!(run(b"\x1bO\x1b"), State::Escape);
+        assert_eq!(run(b"\x1b(O"), State::Ground);
+    }
+}
@@ -0,0 +1,682 @@
+//! Incremental parsing of input that arrives in chunks.
+//!
+//! ```
+//! use csi_parser::iter::Output;
+//! use csi_parser::stream::StreamParser;
+//!
+//! let mut parser = StreamParser::new();
+//! let mut out = vec![];
+//!
+//! for chunk in [&b"hello \x1b[3"[..], b"1mw\xc3", b"\xb6rld"] {
+//!     parser.feed(chunk, |o| match o {
+//!         Output::Text(text) => out.push(text.to_string()),
+//!         Output::Escape(seq) => out.push(seq.to_string()),
+//!         _ => {}
+//!     });
+//! }
+//!
+//! assert_eq!(out, vec!["hello ", "[31m", "w", "ö", "rld"]);
+//! ```
+
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+use crate::config::ParserConfig;
+use crate::enums::C0;
+use crate::iter::Output;
+use crate::parser::{DcsMatch, OscMatch, SingleShift, StringMatch};
+use crate::scanner::{Scanner, C1};
+use crate::state::{Action, State};
+
+/// A parser that keeps unfinished sequences and UTF-8 characters between
+/// calls to [`StreamParser::feed`].
+///
+/// Text is emitted as soon as it is complete, so a text run may be split into
+/// several [`Output::Text`] items. Invalid UTF-8 is replaced by `U+FFFD`, also
+/// in the payloads of control strings.
+///
+/// The data string of a DCS sequence is not buffered either, it is emitted
+/// in [`Output::DcsPut`] items as it arrives.
+///
+/// The `start` and `end` of emitted matches are offsets into the stream,
+/// counting every byte fed since the parser was created.
+///
+/// An unfinished sequence going past the limits of [`ParserConfig`] is not
+/// buffered any further, it is emitted in [`Output::Malformed`] items.
+#[derive(Debug, Default)]
+pub struct StreamParser {
+    config: ParserConfig,
+    // 缓存数据末尾的状态
+    state: State,
+    // 缓存数据开始时的状态,在 DCS 数据中为 `DcsPassthrough`
+    start: State,
+    // 未完成的序列或字符
+    buf: Vec<u8>,
+    // 已输入的字节数,即当前块在流中的位置
+    pos: usize,
+    // 正在跳过超出限制的序列
+    discard: bool,
+    // 跳过的字符串以 ESC 结尾,可能是 `ST` 的一部分
+    discard_esc: bool,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser with the given config.
+    ///
+    /// With [`ParserConfig::c1_controls`] the bytes `0x80–0x9F` are C1
+    /// controls rather than UTF-8 continuation bytes. C0 controls are emitted
+    /// as [`Output::Control`] unless [`ParserConfig::controls_in_text`] is
+    /// set, and unfinished sequences are only buffered up to the limits.
+    pub fn with_config(config: ParserConfig) -> Self {
+        StreamParser {
+            config,
+            ..Self::default()
+        }
+    }
+
+    fn scanner<'a>(&self, bytes: &'a [u8]) -> Scanner<'a> {
+        Scanner::new(bytes, C1::new::<[u8]>(self.config))
+            .limits(self.config)
+            .split_controls(!self.config.controls_in_text)
+            .report_errors()
+    }
+
+    /// Feeds the next chunk of input, calling `f` for every complete output.
+    pub fn feed<F: FnMut(Output<'_>)>(&mut self, bytes: &[u8], mut f: F) {
+        let chunk = bytes;
+        let mut bytes = bytes;
+
+        if self.discard {
+            bytes = &bytes[self.discard(bytes, &mut f)..];
+            if self.discard {
+                self.pos += chunk.len();
+                return;
+            }
+        }
+
+        // 先补全上次未完成的部分
+        if !self.buf.is_empty() {
+            match self.complete_len(bytes) {
+                Some(len) => {
+                    // 缓存的是当前块之前的字节
+                    let offset = self.pos + (chunk.len() - bytes.len()) - self.buf.len();
+                    self.buf.extend_from_slice(&bytes[..len]);
+                    let mut scanner = self.scanner(&self.buf).with_state(self.start).keep_dcs();
+                    emit(&self.buf, offset, &mut scanner, &mut f);
+                    self.buf.clear();
+                    bytes = &bytes[len..];
+                }
+                None => {
+                    self.buf.extend_from_slice(bytes);
+                    self.pos += chunk.len();
+                    self.check_limits(&mut f);
+                    return;
+                }
+            }
+        }
+
+        let offset = self.pos + (chunk.len() - bytes.len());
+        let mut scanner = self.scanner(bytes).partial().with_state(self.state);
+        emit(bytes, offset, &mut scanner, &mut f);
+        self.pos += chunk.len();
+
+        self.state = scanner.state();
+        self.start = scanner.resume_state();
+        self.buf.extend_from_slice(&bytes[scanner.consumed()..]);
+        self.check_limits(&mut f);
+    }
+
+    /// Ends the stream, emitting whatever is left. An unfinished sequence is
+    /// emitted as [`Output::Malformed`].
+    pub fn flush<F: FnMut(Output<'_>)>(&mut self, mut f: F) {
+        if core::mem::take(&mut self.discard_esc) {
+            f(Output::Malformed("\x1b"));
+        }
+        self.discard = false;
+
+        let offset = self.pos - self.buf.len();
+        let mut scanner = self.scanner(&self.buf).with_state(self.start);
+        emit(&self.buf, offset, &mut scanner, &mut f);
+        self.buf.clear();
+        self.state = State::Ground;
+        self.start = State::Ground;
+    }
+
+    /// Starts skipping the buffered sequence if it is already past the limits.
+    ///
+    /// A string counts its introducer, which is at most 2 bytes, so it is only
+    /// skipped once it is certainly too long.
+    fn check_limits<F: FnMut(Output<'_>)>(&mut self, f: &mut F) {
+        if self.start != State::Ground {
+            return;
+        }
+        let limit = match self.state {
+            // `ESC` 之前可能是一个字符串
+            State::Ground | State::Escape | State::SingleShift | State::DcsPassthrough => None,
+            state if state.is_string() => self.config.max_string_len.map(|max| max + 2),
+            _ => self.config.max_sequence_len,
+        };
+
+        if limit.is_some_and(|limit| self.buf.len() > limit) {
+            self.emit_skipped(&self.buf, State::Ground, f);
+            self.buf.clear();
+            self.discard = true;
+        }
+    }
+
+    /// Skips the rest of a sequence that is past the limits, returns the
+    /// number of bytes skipped.
+    ///
+    /// The sequence ends at its final byte or terminator, or before a byte
+    /// that aborts it, which is then parsed as usual.
+    fn discard<F: FnMut(Output<'_>)>(&mut self, bytes: &[u8], f: &mut F) -> usize {
+        // 上一块末尾的 ESC
+        if core::mem::take(&mut self.discard_esc) {
+            self.discard = false;
+            if bytes.first() == Some(&b'\\') {
+                self.state = State::Ground;
+                f(Output::Malformed("\x1b\\"));
+                return 1;
+            }
+            // ESC 开始了新的序列
+            self.buf.push(0x1b);
+            self.state = State::Escape;
+            return 0;
+        }
+
+        let state = self.state;
+        let c1 = C1::new::<[u8]>(self.config);
+        let mut end = bytes.len();
+        for (index, &byte) in bytes.iter().enumerate() {
+            let code = c1.code(&bytes[index..]);
+            let is_string = self.state.is_string();
+            let aborts = match code {
+                Some((0x9c, _)) => !is_string,
+                Some(_) => true,
+                None => matches!(byte, 0x18 | 0x1a) || (byte == 0x1b && !is_string),
+            };
+
+            // 字符串中的 ESC,只有 `ESC \` 属于该序列
+            if is_string && byte == 0x1b {
+                match bytes.get(index + 1) {
+                    Some(b'\\') => end = index + 2,
+                    Some(_) => end = index,
+                    None => {
+                        self.discard_esc = true;
+                        self.emit_skipped(&bytes[..index], state, f);
+                        return bytes.len();
+                    }
+                }
+                self.state = State::Ground;
+                self.discard = false;
+                break;
+            }
+            if aborts {
+                self.state = State::Ground;
+                self.discard = false;
+                end = index;
+                break;
+            }
+
+            self.state = match code {
+                Some((code, _)) => self.state.advance_c1(code).0,
+                None => self.state.advance(byte).0,
+            };
+            if self.state == State::Ground {
+                self.discard = false;
+                end = index + 1;
+                break;
+            }
+        }
+
+        self.emit_skipped(&bytes[..end], state, f);
+        end
+    }
+
+    /// Emits the bytes of a skipped sequence, starting in `state`, as
+    /// [`Output::Malformed`] items, split by the C0 controls executed inside
+    /// it.
+    fn emit_skipped<F: FnMut(Output<'_>)>(&self, bytes: &[u8], mut state: State, f: &mut F) {
+        if self.config.controls_in_text {
+            return emit_lossy(bytes, Output::Malformed, f);
+        }
+
+        let c1 = C1::new::<[u8]>(self.config);
+        let mut start = 0;
+        for (index, &byte) in bytes.iter().enumerate() {
+            let code = c1.code(&bytes[index..]);
+            let action;
+            (state, action) = match code {
+                Some((code, _)) => state.advance_c1(code),
+                None => state.advance(byte),
+            };
+            if let (Action::Execute, None, Ok(c0)) = (action, code, C0::try_from(byte)) {
+                emit_lossy(&bytes[start..index], Output::Malformed, f);
+                f(Output::Control(c0));
+                start = index + 1;
+            }
+        }
+        emit_lossy(&bytes[start..], Output::Malformed, f);
+    }
+
+    /// Number of bytes of `bytes` needed to finish the buffered sequence or
+    /// character, `None` if all of them are not enough.
+    ///
+    /// A sequence is finished when it is back in ground state, or when the
+    /// header of a DCS sequence is complete.
+    fn complete_len(&mut self, bytes: &[u8]) -> Option<usize> {
+        // 未完成的UTF-8字符
+        if matches!(self.state, State::Ground | State::DcsPassthrough) {
+            let width = match self.buf[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            let need = width - self.buf.len();
+            // 启用 C1 时 0x80–0x9F 是控制字符,不是后续字节
+            let c1 = self.config.c1_controls;
+            let ends = |b: u8| b & 0xc0 != 0x80 || (c1 && b <= 0x9f);
+
+            return match bytes.iter().position(|&b| ends(b)) {
+                // 遇到非后续字节,字符无效
+                Some(pos) if pos < need => Some(pos),
+                _ if bytes.len() < need => None,
+                _ => Some(need),
+            };
+        }
+
+        let c1 = C1::new::<[u8]>(self.config);
+        for (index, &byte) in bytes.iter().enumerate() {
+            self.state = match c1.code(&bytes[index..]) {
+                Some((code, _)) => self.state.advance_c1(code).0,
+                None => self.state.advance(byte).0,
+            };
+            if matches!(self.state, State::Ground | State::DcsPassthrough) {
+                return Some(index + 1);
+            }
+        }
+
+        None
+    }
+}
+
+/// Emits the outputs of `bytes`, which start at `offset` in the stream.
+fn emit<F: FnMut(Output<'_>)>(bytes: &[u8], offset: usize, scanner: &mut Scanner, f: &mut F) {
+    while let Some(token) = scanner.next() {
+        emit_output(Output::new(bytes, token, scanner.params()), offset, f);
+    }
+}
+
+/// Emits `bytes` wrapped by `wrap`, invalid UTF-8 is replaced by `U+FFFD`.
+fn emit_lossy<'a, F: FnMut(Output<'_>)>(
+    bytes: &'a [u8],
+    wrap: fn(&'a str) -> Output<'a>,
+    f: &mut F,
+) {
+    for chunk in bytes.utf8_chunks() {
+        if !chunk.valid().is_empty() {
+            f(wrap(chunk.valid()));
+        }
+        if !chunk.invalid().is_empty() {
+            f(wrap("\u{FFFD}"));
+        }
+    }
+}
+
+/// Converts an output to `str`, invalid UTF-8 is replaced by `U+FFFD`, and
+/// moves its span by `offset`.
+fn emit_output<F: FnMut(Output<'_>)>(out: Output<'_, [u8]>, offset: usize, f: &mut F) {
+    match out {
+        Output::Text(text) => emit_lossy(text, Output::Text, f),
+        Output::Escape(seq) => f(Output::Escape(seq)),
+        Output::Esc(seq) => f(Output::Esc(seq)),
+        Output::SingleShift(shift) => f(Output::SingleShift(SingleShift {
+            start: shift.start + offset,
+            end: shift.end + offset,
+            ..shift
+        })),
+        Output::Control(c0) => f(Output::Control(c0)),
+        Output::DcsHook(dcs) => f(Output::DcsHook(DcsMatch {
+            start: dcs.start + offset,
+            end: dcs.end + offset,
+            raw_params: &String::from_utf8_lossy(dcs.raw_params),
+            intermediates: &String::from_utf8_lossy(dcs.intermediates),
+            dcs_type: dcs.dcs_type,
+        })),
+        Output::DcsPut(data) => emit_lossy(data, Output::DcsPut, f),
+        Output::DcsUnhook => f(Output::DcsUnhook),
+        Output::Malformed(bytes) => emit_lossy(bytes, Output::Malformed, f),
+        Output::Osc(osc) => f(Output::Osc(OscMatch {
+            start: osc.start + offset,
+            end: osc.end + offset,
+            payload: &String::from_utf8_lossy(osc.payload),
+            terminator: osc.terminator,
+        })),
+        Output::SosPmApc(string) => f(Output::SosPmApc(StringMatch {
+            start: string.start + offset,
+            end: string.end + offset,
+            kind: string.kind,
+            payload: &String::from_utf8_lossy(string.payload),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
+    use alloc::{
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+
+    #[cfg(feature = "std")]
+    use std::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use super::*;
+    use crate::enums::{
+        CSISequence, ClearMode, Color, EscSequence, NamedColor, SgrAttr, SgrAttrs, C0,
+    };
+    use crate::iter::CsiParser;
+
+    #[derive(Debug, PartialEq)]
+    enum Owned {
+        Text(String),
+        Escape(CSISequence),
+        Esc(EscSequence),
+        SingleShift(u8),
+        Control(C0),
+        Osc(Option<u16>, String),
+        SosPmApc(String),
+        DcsHook(String, u8),
+        DcsPut(String),
+        DcsUnhook,
+        Malformed(String),
+    }
+
+    /// Collects the outputs, joining adjacent text and DCS data.
+    fn push(out: &mut Vec<Owned>, o: Output) {
+        match (out.last_mut(), o) {
+            (Some(Owned::Text(last)), Output::Text(text)) => last.push_str(text),
+            (_, Output::Text(text)) => out.push(Owned::Text(text.to_string())),
+            (_, Output::Escape(seq)) => out.push(Owned::Escape(seq)),
+            (_, Output::Esc(seq)) => out.push(Owned::Esc(seq)),
+            (_, Output::SingleShift(shift)) => out.push(Owned::SingleShift(shift.character)),
+            (_, Output::Control(c0)) => out.push(Owned::Control(c0)),
+            (_, Output::Osc(osc)) => out.push(Owned::Osc(osc.command(), osc.payload.to_string())),
+            (_, Output::SosPmApc(string)) => out.push(Owned::SosPmApc(string.payload.to_string())),
+            (_, Output::DcsHook(dcs)) => {
+                out.push(Owned::DcsHook(dcs.raw_params.to_string(), dcs.dcs_type))
+            }
+            (Some(Owned::DcsPut(last)), Output::DcsPut(data)) => last.push_str(data),
+            (_, Output::DcsPut(data)) => out.push(Owned::DcsPut(data.to_string())),
+            (_, Output::DcsUnhook) => out.push(Owned::DcsUnhook),
+            (Some(Owned::Malformed(last)), Output::Malformed(bytes)) => last.push_str(bytes),
+            (_, Output::Malformed(bytes)) => out.push(Owned::Malformed(bytes.to_string())),
+        }
+    }
+
+    fn stream(chunks: &[&[u8]]) -> Vec<Owned> {
+        stream_with(ParserConfig::default(), chunks)
+    }
+
+    fn stream_with(config: ParserConfig, chunks: &[&[u8]]) -> Vec<Owned> {
+        let mut parser = StreamParser::with_config(config);
+        let mut out = vec![];
+        for chunk in chunks {
+            parser.feed(chunk, |o| push(&mut out, o));
+        }
+        parser.flush(|o| push(&mut out, o));
+        out
+    }
+
+    #[test]
+    fn split_everywhere() {
+        let text = "👋,\r\n \x1b[31;4m🌍\x1b[0m!\x1b]0;title\x07\x1b[?25h\x1bP1$qü\x1b\\\x1b[1<2m\x1b[3\x18\x1bPqö\x1b[m\x1b_Gi=1;\x1b\\\x1b(0q\x1b7\x1bOP\x1b[1\n2m\x1b\t8\x1bO";
+
+        let mut expected = vec![];
+        text.csi_parser().for_each(|o| push(&mut expected, o));
+
+        let bytes = text.as_bytes();
+        for i in 0..bytes.len() {
+            for j in i..bytes.len() {
+                let out = stream(&[&bytes[..i], &bytes[i..j], &bytes[j..]]);
+                assert_eq!(out, expected, "split at {} and {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn stream_offsets() {
+        fn span(o: Output) -> Option<(usize, usize)> {
+            match o {
+                Output::SingleShift(shift) => Some((shift.start, shift.end)),
+                Output::Osc(osc) => Some((osc.start, osc.end)),
+                Output::SosPmApc(string) => Some((string.start, string.end)),
+                Output::DcsHook(dcs) => Some((dcs.start, dcs.end)),
+                _ => None,
+            }
+        }
+
+        let text = "ab\x1b]0;title\x07c\x1bOPd\x1b_Gi=1\x1b\\\x1bP1$qm\x1b\\e";
+        let expected: Vec<_> = text.csi_parser().filter_map(span).collect();
+        assert_eq!(expected, vec![(2, 12), (13, 16), (17, 25), (25, 30)]);
+
+        let bytes = text.as_bytes();
+        for i in 0..bytes.len() {
+            for j in i..bytes.len() {
+                let mut parser = StreamParser::new();
+                let mut out = vec![];
+                for chunk in [&bytes[..i], &bytes[i..j], &bytes[j..]] {
+                    parser.feed(chunk, |o| out.extend(span(o)));
+                }
+                parser.flush(|o| out.extend(span(o)));
+                assert_eq!(out, expected, "split at {} and {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn split_everywhere_c1() {
+        let config = ParserConfig {
+            c1_controls: true,
+            ..ParserConfig::default()
+        };
+        let bytes = b"a\xe4\x9b2J\xc3\xbc\x9d0;t\xc3\xb6\x9c\xe4\x9b1mb\x90q\xe4\x9c\x9b?25h";
+
+        let expected = stream_with(config, &[bytes]);
+        assert_eq!(
+            stream_with(config, &[b"\xe4\x9b2J"]),
+            vec![
+                Owned::Text("\u{FFFD}".to_string()),
+                Owned::Escape(CSISequence::EraseDisplay(ClearMode::All)),
+            ]
+        );
+        for i in 0..bytes.len() {
+            for j in i..bytes.len() {
+                let out = stream_with(config, &[&bytes[..i], &bytes[i..j], &bytes[j..]]);
+                assert_eq!(out, expected, "split at {} and {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_strings() {
+        assert_eq!(
+            stream(&[b"\x1b]0;a\xff", b"b\x07\x1b_\xe4\x1b\\"]),
+            vec![
+                Owned::Osc(Some(0), "0;a\u{FFFD}b".to_string()),
+                Owned::SosPmApc("\u{FFFD}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn emit_early() {
+        let mut parser = StreamParser::new();
+        let mut out = vec![];
+
+        parser.feed(b"ab\x1b[3", |o| push(&mut out, o));
+        assert_eq!(out, vec![Owned::Text("ab".to_string())]);
+
+        parser.feed(b"1m", |o| push(&mut out, o));
+        assert_eq!(
+            out,
+            vec![
+                Owned::Text("ab".to_string()),
+                Owned::Escape(CSISequence::Sgr(SgrAttrs::from_slice(&[
+                    SgrAttr::Foreground(Color::Named(NamedColor::Red))
+                ]))),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert_eq!(
+            stream(&[b"a\xe4", b"b\xff", b"\xe4\xb8"]),
+            vec![Owned::Text("a\u{FFFD}b\u{FFFD}\u{FFFD}".to_string())]
+        );
+    }
+
+    #[test]
+    fn flush_unfinished_sequence() {
+        assert_eq!(
+            stream(&[b"a\x1b[3", b"1"]),
+            vec![
+                Owned::Text("a".to_string()),
+                Owned::Malformed("\x1b[31".to_string())
+            ]
+        );
+        assert_eq!(
+            stream(&[b"\x1bPqa", b"b\x1b"]),
+            vec![
+                Owned::DcsHook(String::new(), b'q'),
+                Owned::DcsPut("ab".to_string()),
+                Owned::DcsUnhook,
+                Owned::Malformed("\x1b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn c1_controls() {
+        let config = ParserConfig {
+            c1_controls: true,
+            ..ParserConfig::default()
+        };
+        let mut parser = StreamParser::with_config(config);
+        let mut out = vec![];
+        for chunk in [&b"a\x9b3"[..], b"1mb\x9d0;", b"t\x9c"] {
+            parser.feed(chunk, |o| push(&mut out, o));
+        }
+
+        assert_eq!(
+            out,
+            vec![
+                Owned::Text("a".to_string()),
+                Owned::Escape(CSISequence::Sgr(SgrAttrs::from_slice(&[
+                    SgrAttr::Foreground(Color::Named(NamedColor::Red))
+                ]))),
+                Owned::Text("b".to_string()),
+                Owned::Osc(Some(0), "0;t".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_osc() {
+        assert_eq!(
+            stream(&[b"\x1b]0;ti", b"tle\x1b", b"\\a"]),
+            vec![
+                Owned::Osc(Some(0), "0;title".to_string()),
+                Owned::Text("a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn dcs_is_not_buffered() {
+        let mut parser = StreamParser::new();
+        let mut out = vec![];
+
+        parser.feed(b"\x1bP0;1", |o| push(&mut out, o));
+        assert!(out.is_empty());
+
+        parser.feed(b"q#0\xc3", |o| push(&mut out, o));
+        parser.feed(b"\xa9!", |o| push(&mut out, o));
+        assert_eq!(
+            out,
+            vec![
+                Owned::DcsHook("0;1".to_string(), b'q'),
+                Owned::DcsPut("#0é!".to_string()),
+            ]
+        );
+        assert!(parser.buf.is_empty());
+
+        parser.feed(b"~\x1b", |o| push(&mut out, o));
+        parser.feed(b"\\a", |o| push(&mut out, o));
+        assert_eq!(
+            out[1..],
+            [
+                Owned::DcsPut("#0é!~".to_string()),
+                Owned::DcsUnhook,
+                Owned::Text("a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let config = ParserConfig {
+            max_sequence_len: Some(6),
+            max_string_len: Some(4),
+            ..ParserConfig::default()
+        };
+        let text = "a\x1b[1;2;3;4mb\x1b]0;title\x1b\\c\x1b]0;title\x07\x1b]0;title\x1b[m\x1b[12345\x18\x1b]0;t\x07\x1b_abcdef";
+
+        let mut expected = vec![];
+        text.csi_parser_with(config)
+            .for_each(|o| push(&mut expected, o));
+        assert!(expected.contains(&Owned::Osc(Some(0), "0;t".to_string())));
+
+        let bytes = text.as_bytes();
+        for i in 0..bytes.len() {
+            let out = stream_with(config, &[&bytes[..i], &bytes[i..]]);
+            assert_eq!(out, expected, "split at {}", i);
+        }
+        let chunks: Vec<_> = bytes.chunks(1).collect();
+        assert_eq!(stream_with(config, &chunks), expected);
+
+        // 跳过的序列中执行的控制字符
+        assert_eq!(
+            stream_with(config, &[b"\x1b[1;\n2;3", b";4\r;5mx"]),
+            vec![
+                Owned::Malformed("\x1b[1;".to_string()),
+                Owned::Control(C0::Lf),
+                Owned::Malformed("2;3;4".to_string()),
+                Owned::Control(C0::Cr),
+                Owned::Malformed(";5m".to_string()),
+                Owned::Text("x".to_string()),
+            ]
+        );
+
+        // 超出限制的序列不再缓存
+        let mut parser = StreamParser::with_config(config);
+        parser.feed(b"\x1b]0;", |_| {});
+        for _ in 0..100 {
+            parser.feed(b"title", |_| {});
+        }
+        assert!(parser.buf.is_empty());
+    }
+}